@@ -1,161 +1,1672 @@
+use std::collections::{HashMap, HashSet};
+
 use crate::{
-    ast::TypeExpr,
+    ast::{Identifier, RecordTypeMemeber, Span, TypeExpr, TypeIdentifier},
     constraints::{Constraint, ConstraintKind},
-    scope::ScopeTree,
+    diagnostics::Diagnostic,
+    scope::{instantiate, substitute_type_vars, ScopeTree},
 };
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct AnalyzeError {
+    pub code: &'static str,
     pub message: String,
     pub lhs: TypeExpr,
     pub rhs: TypeExpr,
+    pub span: Span,
+    /** where the expected type came from, if it was written down */
+    pub expected_span: Option<Span>,
 }
 
-type AnalyzeResult = Result<(), AnalyzeError>;
-
-pub fn analyze_scope_tree(
-    constraints: Vec<Constraint>,
-    scope_tree: &mut ScopeTree,
-) -> AnalyzeResult {
-    for constraint in constraints {
-        unify(constraint, scope_tree)?;
+impl AnalyzeError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        let label = match self.code {
+            "E0103" => format!("{} has no such member", self.lhs),
+            "E0102" => format!("{} would have to contain itself", self.lhs),
+            "E0107" => format!("{} can't be printed", self.lhs),
+            "E0108" => format!("wrong amount of type args for {}", self.lhs),
+            "E0110" => format!("wrong amount of bindings for {}", self.lhs),
+            _ => format!("expected {}, found {}", self.lhs, self.rhs),
+        };
+        let diagnostic =
+            Diagnostic::error(self.code, self.message.clone()).with_primary(self.span, label);
+        let diagnostic = match (&self.lhs, &self.rhs) {
+            (TypeExpr::Opaque(type_identifier, _), _)
+            | (_, TypeExpr::Opaque(type_identifier, _))
+                if self.code == "E0109" =>
+            {
+                let name = type_identifier.name.join(".");
+                diagnostic.with_note(format!(
+                    "make one with {}(...) and get the value back with {}.unwrap(...)",
+                    name, name
+                ))
+            }
+            _ => diagnostic,
+        };
+        match self.expected_span {
+            Some(expected_span) => {
+                diagnostic.with_secondary(expected_span, "expected because of this annotation")
+            }
+            None => diagnostic,
+        }
     }
-    scope_tree.apply_substitutions();
-    Ok(())
 }
 
-pub fn unify(constraint: Constraint, scope_tree: &mut ScopeTree) -> AnalyzeResult {
-    println!(
-        "\nConstraint ({}):\n{:#?}\n{}\n{:#?}",
-        constraint.scope_index, constraint.lhs, constraint.kind, constraint.rhs
-    );
-    let resolve_left = scope_tree.resolve_type(constraint.clone().lhs, constraint.scope_index);
-    let resolve_right = scope_tree.resolve_type(constraint.clone().rhs, constraint.scope_index);
-    println!(
-        "Resolved:\n{:#?}\n{}\n{:#?}\n\n",
-        resolve_left, constraint.kind, resolve_right
-    );
-
-    match (resolve_left.clone(), resolve_right.clone()) {
-        (TypeExpr::Number, TypeExpr::Number) => Ok(()),
-        (TypeExpr::String, TypeExpr::String) => Ok(()),
-        (TypeExpr::Void, TypeExpr::Void) => Ok(()),
-
-        (TypeExpr::InferenceRequired(Some(type_iden)), _) => {
-            println!(
-                "Setting inferred type {} to {:?}",
-                type_iden.clone().name.join("."),
-                resolve_right.clone()
-            );
-            scope_tree.update_type_symbol(constraint.scope_index, type_iden, resolve_right.clone());
+type AnalyzeResult = Result<(), Box<AnalyzeError>>;
+
+/**
+ * What each type var was solved to. Solving only reads the scope tree, so the
+ * same tree can be analysed again, e.g. after an edit.
+ *
+ * Bindings can point at other vars, resolve follows the chain to its end.
+ * Vars minted while solving are named i0, i1... so they can't clash with the
+ * scope tree's t0, t1...
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Substitution {
+    bindings: HashMap<String, TypeExpr>,
+    next_type_var: usize,
+    /**
+     * Vars that got a variant from a value put into them, like the type of an
+     * if. Only those can be widened to the enum, a var that got its variant
+     * from where it's used, like a param passed on to `(dog: Animal.Dog)`,
+     * has to stay that variant
+     */
+    widenable: HashSet<String>,
+}
+
+impl Substitution {
+    /**
+     * Adds the solution for more constraints, e.g. from the next module.
+     * Solving carries on past a failed constraint so every error is reported
+     */
+    pub fn solve(
+        &mut self,
+        constraints: Vec<Constraint>,
+        scope_tree: &ScopeTree,
+    ) -> Result<(), Vec<AnalyzeError>> {
+        let mut errors = Vec::new();
+        for constraint in constraints {
+            if let Err(error) = self.unify(constraint.clone(), scope_tree) {
+                errors.push(self.readable_error(*error, &constraint, scope_tree));
+                self.poison(&constraint, scope_tree);
+            }
+        }
+
+        if errors.is_empty() {
             Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /**
+     * Fills in what's been solved so far and renames the vars that are still
+     * unsolved to a, b, c... in the order they show up
+     */
+    fn readable_error(
+        &self,
+        error: AnalyzeError,
+        constraint: &Constraint,
+        scope_tree: &ScopeTree,
+    ) -> AnalyzeError {
+        let lhs = self.apply(error.lhs, scope_tree, constraint.scope_index);
+        let rhs = self.apply(error.rhs, scope_tree, constraint.scope_index);
+        let mut unsolved = Vec::new();
+        collect_free_type_vars(&lhs, &mut unsolved);
+        collect_free_type_vars(&rhs, &mut unsolved);
+        let readable_names: HashMap<String, TypeExpr> = unsolved
+            .into_iter()
+            .enumerate()
+            .map(|(index, var_name)| {
+                let letter = (b'a' + (index % 26) as u8) as char;
+                let readable_name = match index / 26 {
+                    0 => letter.to_string(),
+                    round => format!("{}{}", letter, round),
+                };
+                (
+                    var_name,
+                    TypeExpr::InferenceRequired(Some(TypeIdentifier {
+                        name: vec![readable_name],
+                    })),
+                )
+            })
+            .collect();
+        AnalyzeError {
+            lhs: substitute_type_vars(lhs, &readable_names),
+            rhs: substitute_type_vars(rhs, &readable_names),
+            ..error
         }
+    }
 
-        (
+    /**
+     * Binds whatever is still unsolved in a failed constraint to the error
+     * type, so the one mistake doesn't show up again everywhere it's used
+     */
+    fn poison(&mut self, constraint: &Constraint, scope_tree: &ScopeTree) {
+        let sides = [constraint.lhs.clone(), constraint.rhs.clone()];
+        for side in sides {
+            for var_name in self.free_type_vars(side, scope_tree, constraint.scope_index) {
+                self.bindings.insert(var_name, TypeExpr::Error);
+            }
+        }
+    }
+
+    /** follows bound vars and declared type names until neither applies */
+    pub fn resolve(
+        &self,
+        type_expr: TypeExpr,
+        scope_tree: &ScopeTree,
+        scope_index: usize,
+    ) -> TypeExpr {
+        match type_expr {
+            TypeExpr::InferenceRequired(Some(ref type_var)) => {
+                match self.bindings.get(&type_var.name.join(".")) {
+                    Some(bound) => self.resolve(bound.clone(), scope_tree, scope_index),
+                    None => type_expr,
+                }
+            }
+            // generic type decs are schemes, their args go in place of the type vars
+            TypeExpr::GenericRef(ref type_identifier, ref type_args) => {
+                match scope_tree
+                    .resolve_type(TypeExpr::TypeRef(type_identifier.clone()), scope_index)
+                {
+                    TypeExpr::Scheme {
+                        type_vars,
+                        type_expr: generic_type,
+                    } if type_vars.len() == type_args.len() => {
+                        let type_args: HashMap<String, TypeExpr> = type_vars
+                            .iter()
+                            .map(|type_var| type_var.name.join("."))
+                            .zip(type_args.iter().cloned())
+                            .collect();
+                        self.resolve(
+                            substitute_type_vars(*generic_type, &type_args),
+                            scope_tree,
+                            scope_index,
+                        )
+                    }
+                    _ => type_expr,
+                }
+            }
+            TypeExpr::TypeRef(_) => {
+                let resolved = scope_tree.resolve_type(type_expr.clone(), scope_index);
+                if resolved == type_expr {
+                    resolved
+                } else {
+                    self.resolve(resolved, scope_tree, scope_index)
+                }
+            }
+            _ => type_expr,
+        }
+    }
+
+    /**
+     * Like resolve, but also resolves the types nested inside functions and
+     * records. Type refs nested in declared types are left alone, they can be
+     * recursive
+     */
+    pub fn apply(
+        &self,
+        type_expr: TypeExpr,
+        scope_tree: &ScopeTree,
+        scope_index: usize,
+    ) -> TypeExpr {
+        let apply = |type_expr| self.apply(type_expr, scope_tree, scope_index);
+        match self.resolve(type_expr, scope_tree, scope_index) {
             TypeExpr::FunctionDefinition {
-                type_identifier: _left_type_identifier,
-                parameters: left_params,
-                return_type: left_return_type,
+                type_identifier,
+                parameters,
+                return_type,
+            } => TypeExpr::FunctionDefinition {
+                type_identifier,
+                parameters: parameters.into_iter().map(apply).collect(),
+                return_type: Box::new(apply(*return_type)),
             },
-            TypeExpr::FunctionDefinition {
-                type_identifier: _right_type_identifer,
-                parameters: right_params,
-                return_type: right_return_type,
+            TypeExpr::FunctionCall {
+                args,
+                return_type,
+                callee,
+            } => TypeExpr::FunctionCall {
+                args: args.into_iter().map(apply).collect(),
+                return_type: Box::new(apply(*return_type)),
+                callee,
             },
-        ) => {
-            if left_params.len() != right_params.len() {
-                return Err(AnalyzeError {
-                    message: "Param counts don't match".to_string(),
-                    lhs: resolve_left,
-                    rhs: resolve_right,
-                });
+            TypeExpr::Record(members) => TypeExpr::Record(
+                members
+                    .into_iter()
+                    .map(|member| RecordTypeMemeber {
+                        type_expr: apply(member.type_expr),
+                        ..member
+                    })
+                    .collect(),
+            ),
+            TypeExpr::Array(element_type) => TypeExpr::Array(Box::new(apply(*element_type))),
+            TypeExpr::Variant(enum_type, variant) => {
+                TypeExpr::Variant(Box::new(apply(*enum_type)), variant)
             }
+            TypeExpr::GenericRef(type_identifier, type_args) => {
+                TypeExpr::GenericRef(type_identifier, type_args.into_iter().map(apply).collect())
+            }
+            TypeExpr::Scheme {
+                type_vars,
+                type_expr,
+            } => TypeExpr::Scheme {
+                type_vars,
+                type_expr: Box::new(apply(*type_expr)),
+            },
+            resolved => resolved,
+        }
+    }
+
+    /** names of the unsolved type vars in a type, a scheme's own vars aren't free */
+    fn free_type_vars(
+        &self,
+        type_expr: TypeExpr,
+        scope_tree: &ScopeTree,
+        scope_index: usize,
+    ) -> Vec<String> {
+        let mut free_vars = Vec::new();
+        collect_free_type_vars(
+            &self.apply(type_expr, scope_tree, scope_index),
+            &mut free_vars,
+        );
+        free_vars
+    }
+
+    /** type vars of the value symbols visible from scope_index, except `exclude` */
+    fn env_free_type_vars(
+        &self,
+        scope_tree: &ScopeTree,
+        scope_index: usize,
+        exclude: &str,
+    ) -> Vec<String> {
+        let mut free_vars = Vec::new();
+        let mut current_scope = Some(scope_index);
+        while let Some(index) = current_scope {
+            let scope = &scope_tree.scopes[index];
+            for symbol in scope.value_symbols.values() {
+                if index == scope_index && symbol.name == exclude {
+                    continue;
+                }
+                free_vars.extend(self.free_type_vars(symbol.type_expr.clone(), scope_tree, index));
+            }
+            current_scope = scope.parent;
+        }
+        free_vars
+    }
+
+    fn create_type_var(&mut self) -> TypeExpr {
+        let name = format!("i{}", self.next_type_var);
+        self.next_type_var += 1;
+        TypeExpr::InferenceRequired(Some(TypeIdentifier { name: vec![name] }))
+    }
+
+    /** swaps a scheme's vars for fresh ones, other types are returned as is */
+    fn instantiate(&mut self, type_expr: TypeExpr) -> TypeExpr {
+        instantiate(type_expr, || self.create_type_var())
+    }
+
+    fn bind(
+        &mut self,
+        type_var: &TypeIdentifier,
+        type_expr: TypeExpr,
+        constraint: &Constraint,
+        scope_tree: &ScopeTree,
+    ) -> AnalyzeResult {
+        // binding t0 to something containing t0 would make an infinite type
+        let var_name = type_var.name.join(".");
+        if self
+            .free_type_vars(type_expr.clone(), scope_tree, constraint.scope_index)
+            .contains(&var_name)
+        {
+            return Err(Box::new(AnalyzeError {
+                code: "E0102",
+                message: "Infinite type".to_string(),
+                lhs: TypeExpr::InferenceRequired(Some(type_var.clone())),
+                rhs: type_expr,
+                span: constraint.span,
+                expected_span: constraint.expected_span,
+            }));
+        }
+        eprintln!("Setting inferred type {} to {:?}", var_name, type_expr);
+        self.bindings.insert(var_name, type_expr);
+        Ok(())
+    }
 
-            for (index, left_param) in left_params.iter().enumerate() {
-                let right_param = right_params.get(index).expect("Right param at index");
-                unify(
+    pub fn unify(&mut self, constraint: Constraint, scope_tree: &ScopeTree) -> AnalyzeResult {
+        eprintln!(
+            "\nConstraint ({}):\n{:#?}\n{}\n{:#?}",
+            constraint.scope_index, constraint.lhs, constraint.kind, constraint.rhs
+        );
+        let resolve_left = self.resolve(constraint.lhs.clone(), scope_tree, constraint.scope_index);
+        let resolve_right =
+            self.resolve(constraint.rhs.clone(), scope_tree, constraint.scope_index);
+        eprintln!(
+            "Resolved:\n{:#?}\n{}\n{:#?}\n\n",
+            resolve_left, constraint.kind, resolve_right
+        );
+
+        match &constraint.kind {
+            ConstraintKind::Generalize(identifier) => {
+                self.generalize(identifier, &constraint, scope_tree);
+                return Ok(());
+            }
+            ConstraintKind::Instance(type_args) if !type_args.is_empty() => {
+                let (type_vars, type_expr) = match resolve_right.clone() {
+                    TypeExpr::Scheme {
+                        type_vars,
+                        type_expr,
+                    } => (type_vars, *type_expr),
+                    type_expr => (Vec::new(), type_expr),
+                };
+                if type_vars.len() != type_args.len() {
+                    return Err(Box::new(AnalyzeError {
+                        code: "E0108",
+                        message: format!(
+                            "Wrong amount of type args, expected {} but got {}",
+                            type_vars.len(),
+                            type_args.len()
+                        ),
+                        lhs: resolve_right,
+                        rhs: resolve_left,
+                        span: constraint.span,
+                        expected_span: constraint.expected_span,
+                    }));
+                }
+                let explicit_vars: HashMap<String, TypeExpr> = type_vars
+                    .iter()
+                    .map(|type_var| type_var.name.join("."))
+                    .zip(type_args.iter().cloned())
+                    .collect();
+                return self.unify(
+                    Constraint {
+                        lhs: constraint.lhs,
+                        rhs: substitute_type_vars(type_expr, &explicit_vars),
+                        kind: ConstraintKind::Equality,
+                        scope_index: constraint.scope_index,
+                        span: constraint.span,
+                        expected_span: constraint.expected_span,
+                    },
+                    scope_tree,
+                );
+            }
+            ConstraintKind::Instance(_) => {
+                let instance = self.instantiate(resolve_right);
+                return self.unify(
                     Constraint {
-                        lhs: left_param.clone(),
-                        rhs: right_param.clone(),
+                        lhs: constraint.lhs,
+                        rhs: instance,
                         kind: ConstraintKind::Equality,
                         scope_index: constraint.scope_index,
+                        span: constraint.span,
+                        expected_span: constraint.expected_span,
                     },
                     scope_tree,
-                )?
+                );
             }
+            // vars that are still free are fine, fmt.Sprint takes anything
+            ConstraintKind::Printable => {
+                let printed = match &resolve_left {
+                    TypeExpr::Scheme { type_expr, .. } => type_expr.as_ref(),
+                    type_expr => type_expr,
+                };
+                if matches!(
+                    printed,
+                    TypeExpr::FunctionDefinition { .. }
+                        | TypeExpr::Void
+                        | TypeExpr::ExternPackage { .. }
+                ) {
+                    return Err(Box::new(AnalyzeError {
+                        code: "E0107",
+                        message: format!("Can't interpolate a value of type {}", resolve_left),
+                        lhs: resolve_left,
+                        rhs: resolve_right,
+                        span: constraint.span,
+                        expected_span: constraint.expected_span,
+                    }));
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        if resolve_left == resolve_right {
+            return Ok(());
+        }
 
-            unify(
+        if let ConstraintKind::Member(member) = &constraint.kind {
+            let member_type = match &resolve_left {
+                TypeExpr::Record(members) => members
+                    .iter()
+                    .find(|m| m.identifier == *member)
+                    .map(|m| m.type_expr.clone()),
+                TypeExpr::Error => Some(TypeExpr::Error),
+                _ => None,
+            };
+            let Some(member_type) = member_type else {
+                return Err(Box::new(AnalyzeError {
+                    code: "E0103",
+                    message: format!("No member named {}", member.name),
+                    lhs: resolve_left,
+                    rhs: resolve_right,
+                    span: constraint.span,
+                    expected_span: constraint.expected_span,
+                }));
+            };
+            return self.unify(
                 Constraint {
-                    lhs: *left_return_type,
-                    rhs: *right_return_type,
+                    lhs: member_type,
+                    rhs: constraint.rhs,
                     kind: ConstraintKind::Equality,
                     scope_index: constraint.scope_index,
+                    span: constraint.span,
+                    expected_span: constraint.expected_span,
                 },
                 scope_tree,
-            )?;
-            Ok(())
+            );
         }
 
-        // inverse of valid match, swap left and right sides
-        (_, TypeExpr::InferenceRequired(Some(_))) => unify(
-            Constraint {
-                lhs: constraint.rhs,
-                rhs: constraint.lhs,
-                kind: constraint.kind,
-                scope_index: constraint.scope_index,
-            },
-            scope_tree,
-        ),
+        // a narrowed value still matches the patterns of its enum
+        if constraint.kind == ConstraintKind::PatternMatch
+            && (matches!(resolve_left, TypeExpr::Variant(..))
+                || matches!(resolve_right, TypeExpr::Variant(..)))
+        {
+            return self.unify(
+                Constraint {
+                    lhs: self.enum_type(resolve_left, scope_tree, constraint.scope_index),
+                    rhs: self.enum_type(resolve_right, scope_tree, constraint.scope_index),
+                    ..constraint
+                },
+                scope_tree,
+            );
+        }
 
-        (
-            TypeExpr::FunctionCall {
-                args,
-                return_type: call_return_type,
-                ..
-            },
-            TypeExpr::FunctionDefinition {
-                parameters,
-                return_type: def_return_type,
-                ..
-            },
-        ) => {
-            if args.len() != parameters.len() {
-                return Err(AnalyzeError {
-                    message: "Wrong amount of args provided".to_string(),
-                    lhs: resolve_left,
-                    rhs: resolve_right,
+        // a generic type written without its args only shows before resolving
+        let bare_refs = [&constraint.lhs, &constraint.rhs]
+            .into_iter()
+            .filter_map(|side| bare_generic_ref(side, scope_tree, constraint.scope_index));
+        let generic_refs =
+            [&resolve_left, &resolve_right]
+                .into_iter()
+                .filter_map(|side| match side {
+                    TypeExpr::GenericRef(..) => Some(side.clone()),
+                    _ => None,
                 });
+        for side in bare_refs.chain(generic_refs).collect::<Vec<_>>() {
+            let (type_identifier, arg_count) = match &side {
+                TypeExpr::GenericRef(type_identifier, type_args) => {
+                    (type_identifier, type_args.len())
+                }
+                TypeExpr::TypeRef(type_identifier) => (type_identifier, 0),
+                _ => continue,
+            };
+            let Some(type_var_count) =
+                type_var_count(type_identifier, scope_tree, constraint.scope_index)
+            else {
+                continue;
+            };
+            if type_var_count != arg_count {
+                return Err(Box::new(AnalyzeError {
+                    code: "E0108",
+                    message: format!(
+                        "Wrong amount of type args, expected {} but got {}",
+                        type_var_count, arg_count
+                    ),
+                    lhs: side.clone(),
+                    rhs: TypeExpr::Error,
+                    span: constraint.span,
+                    expected_span: constraint.expected_span,
+                }));
+            }
+        }
+
+        match (resolve_left.clone(), resolve_right.clone()) {
+            (TypeExpr::Number, TypeExpr::Number) => Ok(()),
+            (TypeExpr::String, TypeExpr::String) => Ok(()),
+            (TypeExpr::Boolean, TypeExpr::Boolean) => Ok(()),
+            (TypeExpr::Void, TypeExpr::Void) => Ok(()),
+            // records are structural, same member names and each member's type unifies
+            (TypeExpr::Record(left_members), TypeExpr::Record(right_members)) => {
+                let mut left_names: Vec<&String> =
+                    left_members.iter().map(|m| &m.identifier.name).collect();
+                let mut right_names: Vec<&String> =
+                    right_members.iter().map(|m| &m.identifier.name).collect();
+                left_names.sort();
+                right_names.sort();
+                if left_names != right_names {
+                    return Err(Box::new(AnalyzeError {
+                        code: "E0104",
+                        message: "Record members don't match".to_string(),
+                        lhs: resolve_left,
+                        rhs: resolve_right,
+                        span: constraint.span,
+                        expected_span: constraint.expected_span,
+                    }));
+                }
+
+                for left_member in &left_members {
+                    let right_member = right_members
+                        .iter()
+                        .find(|m| m.identifier == left_member.identifier)
+                        .expect("member names were compared");
+                    self.unify(
+                        Constraint {
+                            lhs: left_member.type_expr.clone(),
+                            rhs: right_member.type_expr.clone(),
+                            kind: ConstraintKind::Equality,
+                            scope_index: constraint.scope_index,
+                            span: constraint.span,
+                            expected_span: constraint.expected_span,
+                        },
+                        scope_tree,
+                    )?
+                }
+                Ok(())
+            }
+
+            (TypeExpr::Array(left_element), TypeExpr::Array(right_element)) => self.unify(
+                Constraint {
+                    lhs: *left_element,
+                    rhs: *right_element,
+                    kind: ConstraintKind::Equality,
+                    scope_index: constraint.scope_index,
+                    span: constraint.span,
+                    expected_span: constraint.expected_span,
+                },
+                scope_tree,
+            ),
+
+            (TypeExpr::EnumDec(left_enum), TypeExpr::EnumDec(right_enum))
+                if left_enum.identifier == right_enum.identifier =>
+            {
+                Ok(())
+            }
+
+            // generic enums are nominal, Option<Number> only unifies with another Option
+            (
+                TypeExpr::GenericRef(left_identifier, left_args),
+                TypeExpr::GenericRef(right_identifier, right_args),
+            ) if left_identifier == right_identifier => {
+                for (left_arg, right_arg) in left_args.into_iter().zip(right_args) {
+                    self.unify(
+                        Constraint {
+                            lhs: left_arg,
+                            rhs: right_arg,
+                            kind: ConstraintKind::Equality,
+                            scope_index: constraint.scope_index,
+                            span: constraint.span,
+                            expected_span: constraint.expected_span,
+                        },
+                        scope_tree,
+                    )?
+                }
+                Ok(())
+            }
+
+            // a generic enum written without its args, e.g. `const a: Option = ...`
+            (TypeExpr::EnumDec(enum_dec), TypeExpr::GenericRef(type_identifier, _))
+            | (TypeExpr::GenericRef(type_identifier, _), TypeExpr::EnumDec(enum_dec))
+                if enum_dec.identifier == type_identifier =>
+            {
+                Ok(())
             }
 
-            for (index, arg) in args.iter().enumerate() {
-                let param = parameters.get(index).expect("Right param at index");
-                unify(
+            (TypeExpr::InferenceRequired(Some(type_iden)), _) => {
+                if matches!(resolve_right, TypeExpr::Variant(..)) {
+                    self.widenable.insert(type_iden.name.join("."));
+                }
+                self.bind(&type_iden, resolve_right, &constraint, scope_tree)
+            }
+
+            // unification is symmetric, bind the var on the right just the same
+            (_, TypeExpr::InferenceRequired(Some(type_iden))) => {
+                self.bind(&type_iden, resolve_left, &constraint, scope_tree)
+            }
+
+            // already reported, don't report it again
+            (TypeExpr::Error, _) | (_, TypeExpr::Error) => Ok(()),
+
+            // any other use of a generalized type gets its own instance
+            (TypeExpr::Scheme { .. }, _) | (_, TypeExpr::Scheme { .. }) => {
+                let lhs = self.instantiate(resolve_left);
+                let rhs = self.instantiate(resolve_right);
+                self.unify(
                     Constraint {
-                        lhs: arg.clone(),
-                        rhs: param.clone(),
+                        lhs,
+                        rhs,
                         kind: ConstraintKind::Equality,
                         scope_index: constraint.scope_index,
+                        span: constraint.span,
+                        expected_span: constraint.expected_span,
                     },
                     scope_tree,
-                )?
+                )
             }
 
-            unify(
+            (
+                TypeExpr::Variant(left_enum, left_variant),
+                TypeExpr::Variant(right_enum, right_variant),
+            ) if left_variant == right_variant => self.unify(
                 Constraint {
-                    lhs: *call_return_type,
-                    rhs: *def_return_type,
+                    lhs: *left_enum,
+                    rhs: *right_enum,
                     kind: ConstraintKind::Equality,
                     scope_index: constraint.scope_index,
+                    span: constraint.span,
+                    expected_span: constraint.expected_span,
                 },
                 scope_tree,
-            )?;
-            Ok(())
+            ),
+
+            (TypeExpr::Variant(..), _) | (_, TypeExpr::Variant(..)) => {
+                self.unify_variant(&constraint, resolve_left, resolve_right, scope_tree)
+            }
+
+            (
+                TypeExpr::FunctionDefinition {
+                    type_identifier: _left_type_identifier,
+                    parameters: left_params,
+                    return_type: left_return_type,
+                },
+                TypeExpr::FunctionDefinition {
+                    type_identifier: _right_type_identifer,
+                    parameters: right_params,
+                    return_type: right_return_type,
+                },
+            ) => {
+                if left_params.len() != right_params.len() {
+                    return Err(Box::new(AnalyzeError {
+                        code: "E0105",
+                        message: "Param counts don't match".to_string(),
+                        lhs: resolve_left,
+                        rhs: resolve_right,
+                        span: constraint.span,
+                        expected_span: constraint.expected_span,
+                    }));
+                }
+
+                for (index, left_param) in left_params.iter().enumerate() {
+                    let right_param = right_params.get(index).expect("Right param at index");
+                    self.unify(
+                        Constraint {
+                            lhs: left_param.clone(),
+                            rhs: right_param.clone(),
+                            kind: ConstraintKind::Equality,
+                            scope_index: constraint.scope_index,
+                            span: constraint.span,
+                            expected_span: constraint.expected_span,
+                        },
+                        scope_tree,
+                    )?
+                }
+
+                self.unify(
+                    Constraint {
+                        lhs: *left_return_type,
+                        rhs: *right_return_type,
+                        kind: ConstraintKind::Equality,
+                        scope_index: constraint.scope_index,
+                        span: constraint.span,
+                        expected_span: constraint.expected_span,
+                    },
+                    scope_tree,
+                )?;
+                Ok(())
+            }
+
+            (
+                TypeExpr::FunctionCall {
+                    args,
+                    return_type: call_return_type,
+                    ..
+                },
+                TypeExpr::FunctionDefinition {
+                    parameters,
+                    return_type: def_return_type,
+                    ..
+                },
+            ) => {
+                if args.len() != parameters.len() {
+                    return Err(Box::new(AnalyzeError {
+                        code: "E0106",
+                        message: "Wrong amount of args provided".to_string(),
+                        lhs: resolve_left,
+                        rhs: resolve_right,
+                        span: constraint.span,
+                        expected_span: constraint.expected_span,
+                    }));
+                }
+
+                for (index, arg) in args.iter().enumerate() {
+                    let param = parameters.get(index).expect("Right param at index");
+                    // solved already, so a param the body needs to be a variant isn't widened
+                    let param = self.apply(param.clone(), scope_tree, constraint.scope_index);
+                    self.unify(
+                        Constraint {
+                            lhs: param,
+                            rhs: arg.clone(),
+                            kind: ConstraintKind::Equality,
+                            scope_index: constraint.scope_index,
+                            span: constraint.span,
+                            expected_span: constraint.expected_span,
+                        },
+                        scope_tree,
+                    )?
+                }
+
+                self.unify(
+                    Constraint {
+                        lhs: *call_return_type,
+                        rhs: *def_return_type,
+                        kind: ConstraintKind::Equality,
+                        scope_index: constraint.scope_index,
+                        span: constraint.span,
+                        expected_span: constraint.expected_span,
+                    },
+                    scope_tree,
+                )?;
+                Ok(())
+            }
+
+            // an opaque type needs wrapping before it's used as its underlying type or vice versa
+            (TypeExpr::Opaque(type_identifier, underlying), other)
+            | (other, TypeExpr::Opaque(type_identifier, underlying))
+                if self.resolve(*underlying.clone(), scope_tree, constraint.scope_index)
+                    == other =>
+            {
+                Err(Box::new(AnalyzeError {
+                    code: "E0109",
+                    message: format!(
+                        "{} is opaque, it doesn't match {}",
+                        type_identifier.name.join("."),
+                        other
+                    ),
+                    lhs: resolve_left,
+                    rhs: resolve_right,
+                    span: constraint.span,
+                    expected_span: constraint.expected_span,
+                }))
+            }
+
+            _ => Err(Box::new(AnalyzeError {
+                code: "E0101",
+                message: "Types don't match".to_string(),
+                lhs: resolve_left,
+                rhs: resolve_right,
+                span: constraint.span,
+                expected_span: constraint.expected_span,
+            })),
         }
+    }
+
+    /**
+     * A variant fits where its enum is expected, but the enum doesn't fit
+     * where a variant is. A var that a variant was put into and that then
+     * meets another variant or the enum, like the type of an if with a
+     * branch for each, is widened to the enum
+     */
+    fn unify_variant(
+        &mut self,
+        constraint: &Constraint,
+        resolve_left: TypeExpr,
+        resolve_right: TypeExpr,
+        scope_tree: &ScopeTree,
+    ) -> AnalyzeResult {
+        let left_enum = self.enum_type(resolve_left.clone(), scope_tree, constraint.scope_index);
+        let right_enum = self.enum_type(resolve_right.clone(), scope_tree, constraint.scope_index);
+        let same_enum = self.unify(
+            Constraint {
+                lhs: left_enum.clone(),
+                rhs: right_enum.clone(),
+                kind: ConstraintKind::Equality,
+                scope_index: constraint.scope_index,
+                span: constraint.span,
+                expected_span: constraint.expected_span,
+            },
+            scope_tree,
+        );
 
-        _ => Err(AnalyzeError {
+        let holds_variant = |side: &TypeExpr, resolved: &TypeExpr| match side {
+            TypeExpr::InferenceRequired(Some(type_var))
+                if matches!(resolved, TypeExpr::Variant(..))
+                    && self.widenable.contains(&type_var.name.join(".")) =>
+            {
+                Some(type_var.name.join("."))
+            }
+            _ => None,
+        };
+        let left_is_variant = matches!(resolve_left, TypeExpr::Variant(..));
+        if same_enum.is_ok() {
+            if let Some(var_name) = holds_variant(&constraint.lhs, &resolve_left) {
+                self.bindings.insert(var_name, left_enum);
+                return Ok(());
+            }
+            if !left_is_variant {
+                if let Some(var_name) = holds_variant(&constraint.rhs, &resolve_right) {
+                    self.bindings.insert(var_name, right_enum);
+                }
+                return Ok(());
+            }
+        }
+
+        Err(Box::new(AnalyzeError {
+            code: "E0101",
             message: "Types don't match".to_string(),
             lhs: resolve_left,
             rhs: resolve_right,
-        }),
+            span: constraint.span,
+            expected_span: constraint.expected_span,
+        }))
+    }
+
+    /** the enum a variant belongs to, other types are resolved as is */
+    fn enum_type(
+        &self,
+        type_expr: TypeExpr,
+        scope_tree: &ScopeTree,
+        scope_index: usize,
+    ) -> TypeExpr {
+        match self.resolve(type_expr, scope_tree, scope_index) {
+            TypeExpr::Variant(enum_type, _) => self.enum_type(*enum_type, scope_tree, scope_index),
+            resolved => resolved,
+        }
+    }
+
+    /**
+     * Turns the const's type into a scheme over the type vars that nothing else
+     * in scope refers to, so each reference to the const can use it at another
+     * type
+     */
+    fn generalize(
+        &mut self,
+        identifier: &Identifier,
+        constraint: &Constraint,
+        scope_tree: &ScopeTree,
+    ) {
+        let TypeExpr::InferenceRequired(Some(const_var)) = &constraint.lhs else {
+            // annotated consts are already as general as they'll get
+            return;
+        };
+        let const_type = self.apply(constraint.lhs.clone(), scope_tree, constraint.scope_index);
+        let env_vars =
+            self.env_free_type_vars(scope_tree, constraint.scope_index, &identifier.name);
+        let type_vars: Vec<TypeIdentifier> = self
+            .free_type_vars(const_type.clone(), scope_tree, constraint.scope_index)
+            .into_iter()
+            .filter(|name| !env_vars.contains(name))
+            .map(|name| TypeIdentifier { name: vec![name] })
+            .collect();
+
+        if type_vars.is_empty() {
+            return;
+        }
+        // the const's var now stands for the scheme rather than one instance of it
+        self.bindings.insert(
+            const_var.name.join("."),
+            TypeExpr::Scheme {
+                type_vars,
+                type_expr: Box::new(const_type),
+            },
+        );
+    }
+}
+
+/** how many type args a named type takes, None when there's no such type */
+fn type_var_count(
+    type_identifier: &TypeIdentifier,
+    scope_tree: &ScopeTree,
+    scope_index: usize,
+) -> Option<usize> {
+    let type_ref = TypeExpr::TypeRef(type_identifier.clone());
+    match scope_tree.resolve_type(type_ref.clone(), scope_index) {
+        TypeExpr::Scheme { type_vars, .. } => Some(type_vars.len()),
+        TypeExpr::EnumDec(enum_dec) => Some(enum_dec.type_vars.len()),
+        unresolved if unresolved == type_ref => None,
+        _ => Some(0),
+    }
+}
+
+/**
+ * The first generic type named without type args in an annotation, e.g. the
+ * `Box` in `const b: Box = ...`. Variants and schemes name their enum or
+ * type dec on purpose, so they're skipped
+ */
+fn bare_generic_ref(
+    type_expr: &TypeExpr,
+    scope_tree: &ScopeTree,
+    scope_index: usize,
+) -> Option<TypeExpr> {
+    let find = |type_expr| bare_generic_ref(type_expr, scope_tree, scope_index);
+    match type_expr {
+        TypeExpr::TypeRef(type_identifier) => {
+            match type_var_count(type_identifier, scope_tree, scope_index) {
+                Some(count) if count > 0 => Some(type_expr.clone()),
+                _ => None,
+            }
+        }
+        TypeExpr::FunctionDefinition {
+            parameters,
+            return_type,
+            ..
+        } => parameters
+            .iter()
+            .chain(std::iter::once(return_type.as_ref()))
+            .find_map(find),
+        TypeExpr::Record(members) => members.iter().find_map(|m| find(&m.type_expr)),
+        TypeExpr::Array(element_type) => find(element_type),
+        TypeExpr::GenericRef(_, type_args) => type_args.iter().find_map(find),
+        _ => None,
+    }
+}
+
+fn collect_free_type_vars(type_expr: &TypeExpr, free_vars: &mut Vec<String>) {
+    match type_expr {
+        TypeExpr::InferenceRequired(Some(type_var)) => {
+            let name = type_var.name.join(".");
+            if !free_vars.contains(&name) {
+                free_vars.push(name);
+            }
+        }
+        TypeExpr::FunctionDefinition {
+            parameters,
+            return_type,
+            ..
+        } => {
+            for param in parameters {
+                collect_free_type_vars(param, free_vars);
+            }
+            collect_free_type_vars(return_type, free_vars);
+        }
+        TypeExpr::FunctionCall {
+            args, return_type, ..
+        } => {
+            for arg in args {
+                collect_free_type_vars(arg, free_vars);
+            }
+            collect_free_type_vars(return_type, free_vars);
+        }
+        TypeExpr::Record(members) => {
+            for member in members {
+                collect_free_type_vars(&member.type_expr, free_vars);
+            }
+        }
+        TypeExpr::Array(element_type) => collect_free_type_vars(element_type, free_vars),
+        TypeExpr::Variant(enum_type, _) => collect_free_type_vars(enum_type, free_vars),
+        TypeExpr::GenericRef(_, type_args) => {
+            for type_arg in type_args {
+                collect_free_type_vars(type_arg, free_vars);
+            }
+        }
+        TypeExpr::Scheme {
+            type_vars,
+            type_expr,
+        } => {
+            let mut scheme_free_vars = Vec::new();
+            collect_free_type_vars(type_expr, &mut scheme_free_vars);
+            for name in scheme_free_vars {
+                let is_bound = type_vars.iter().any(|v| v.name.join(".") == name);
+                if !is_bound && !free_vars.contains(&name) {
+                    free_vars.push(name);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use super::*;
+    use crate::{
+        compiler::{CompilerError, ModuleMap},
+        constraints::ConstraintCollector,
+        lexer::Lexer,
+        parser::Parser,
+    };
+
+    fn solve(
+        constraints: Vec<Constraint>,
+        scope_tree: &ScopeTree,
+    ) -> Result<Substitution, Vec<AnalyzeError>> {
+        let mut substitution = Substitution::default();
+        substitution.solve(constraints, scope_tree)?;
+        Ok(substitution)
+    }
+
+    fn analyze_source(source: &str) -> (Result<Substitution, Vec<AnalyzeError>>, ScopeTree, usize) {
+        let tokens = Lexer::new(source.to_string()).tokenize();
+        let program = Parser::new(tokens).parse().expect("source should parse");
+        let mut scope_tree = ScopeTree::new(Arc::new(RwLock::new(ModuleMap::new())));
+        let program = scope_tree
+            .bind_program(program)
+            .expect("source should bind");
+        let program_scope = program.scope.unwrap();
+        let mut collector = ConstraintCollector::new(&mut scope_tree);
+        collector.collect_program(program);
+        let constraints = collector.constraints;
+        let result = solve(constraints, &scope_tree);
+        (result, scope_tree, program_scope)
+    }
+
+    fn value_type(
+        substitution: &Substitution,
+        scope_tree: &ScopeTree,
+        scope_index: usize,
+        name: &str,
+    ) -> TypeExpr {
+        let symbol = scope_tree.find_value_symbol(scope_index, name).unwrap();
+        substitution.apply(symbol.type_expr, scope_tree, scope_index)
+    }
+
+    #[test]
+    fn generalized_const_can_be_used_at_different_types() {
+        let (result, scope_tree, scope) = analyze_source(
+            "module Main
+            const id = (x) => x
+            const n = id(1)
+            const s = id(`one`)",
+        );
+
+        let substitution = result.unwrap();
+        assert!(matches!(
+            value_type(&substitution, &scope_tree, scope, "id"),
+            TypeExpr::Scheme { ref type_vars, .. } if type_vars.len() == 1
+        ));
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope, "n"),
+            TypeExpr::Number
+        );
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope, "s"),
+            TypeExpr::String
+        );
+    }
+
+    #[test]
+    fn solving_leaves_the_scope_tree_alone() {
+        let source = "module Main
+            const double = (x) => x * 2
+            const four = double(2)";
+        let tokens = Lexer::new(source.to_string()).tokenize();
+        let program = Parser::new(tokens).parse().unwrap();
+        let mut scope_tree = ScopeTree::new(Arc::new(RwLock::new(ModuleMap::new())));
+        let program = scope_tree.bind_program(program).unwrap();
+        let program_scope = program.scope.unwrap();
+        let mut collector = ConstraintCollector::new(&mut scope_tree);
+        collector.collect_program(program);
+        let constraints = collector.constraints;
+
+        let scopes_before = scope_tree.scopes.clone();
+        let first = solve(constraints.clone(), &scope_tree).unwrap();
+        assert_eq!(scope_tree.scopes, scopes_before);
+
+        // the same tree can be solved again with the same outcome
+        let second = solve(constraints, &scope_tree).unwrap();
+        for substitution in [first, second] {
+            assert_eq!(
+                value_type(&substitution, &scope_tree, program_scope, "four"),
+                TypeExpr::Number
+            );
+        }
+    }
+
+    #[test]
+    fn vars_from_the_enclosing_function_stay_monomorphic() {
+        let (result, _, _) = analyze_source(
+            "module Main
+            const outer = (y) => {
+                const getY = (z) => y
+                const a = getY(1) + 1
+                getY(`two`) + 2
+            }",
+        );
+
+        assert!(result.is_ok(), "{:?}", result);
+
+        let (result, _, _) = analyze_source(
+            "module Main
+            const outer = (y) => {
+                const getY = (z) => y
+                const a = getY(1) + 1
+                const b: String = getY(`two`)
+                b
+            }",
+        );
+        assert_eq!(result.unwrap_err()[0].message, "Types don't match");
+    }
+
+    #[test]
+    fn occurs_check_rejects_infinite_types() {
+        let (result, _, _) = analyze_source(
+            "module Main
+            const selfApply = (x) => x(x)",
+        );
+
+        assert_eq!(result.unwrap_err()[0].message, "Infinite type");
+    }
+
+    #[test]
+    fn independent_errors_are_all_reported() {
+        let (result, _, _) = analyze_source(
+            "module Main
+            const a: String = 1
+            const b: Number = `two`",
+        );
+
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 2, "{:#?}", errors);
+    }
+
+    #[test]
+    fn poisoned_vars_do_not_cascade() {
+        let (result, _, _) = analyze_source(
+            "module Main
+            const f = (x: Number) => x
+            const bad = f(`one`)
+            const n: String = bad
+            const m: Number = bad",
+        );
+
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1, "{:#?}", errors);
+    }
+
+    #[test]
+    fn operators_check_their_operand_types() {
+        let (result, scope_tree, scope_index) = analyze_source(
+            "module Main
+            const n = -2 ** 3
+            const b = !(n > 1) && n < 2 || n != 3",
+        );
+        let substitution = result.unwrap();
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "n"),
+            TypeExpr::Number
+        );
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "b"),
+            TypeExpr::Boolean
+        );
+
+        let (result, _, _) = analyze_source(
+            "module Main
+            const a = !1
+            const b = 1 && true
+            const c = -true
+            const d = `two` ** 2",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 4, "{:#?}", errors);
+    }
+
+    #[test]
+    fn booleans_can_be_annotated_matched_and_branched_on() {
+        let (result, scope_tree, scope_index) = analyze_source(
+            "module Main
+            const ok: Boolean = 1 < 2
+            const flag = (on: Boolean): String => if on { `yes` } else { `no` }
+            const label = match ok {
+              true -> flag(ok)
+              false -> `off`
+            }
+            const both = ok == true && !false",
+        );
+        let substitution = result.unwrap();
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "ok"),
+            TypeExpr::Boolean
+        );
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "label"),
+            TypeExpr::String
+        );
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "both"),
+            TypeExpr::Boolean
+        );
+
+        let (result, _, _) = analyze_source(
+            "module Main
+            const notBoolean: Boolean = 1
+            const notNumber: Number = true
+            const condition = if `yes` { 1 } else { 2 }",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 3, "{:#?}", errors);
+        assert!(errors.iter().all(|error| error.code == "E0101"));
+    }
+
+    #[test]
+    fn variant_tests_need_an_enum_with_that_variant() {
+        let (result, scope_tree, scope_index) = analyze_source(
+            "module Main
+            enum Animal {
+                Dog(String),
+                Cat
+            }
+            const pet = Animal.Cat
+            const isDog = pet is Animal.Dog
+            const name = (animal) => if animal !is Animal.Cat { `dog` } else { `cat` }",
+        );
+        let substitution = result.unwrap();
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "isDog"),
+            TypeExpr::Boolean
+        );
+        assert!(matches!(
+            value_type(&substitution, &scope_tree, scope_index, "name"),
+            TypeExpr::FunctionDefinition { parameters, .. }
+                if matches!(&parameters[..], [TypeExpr::EnumDec(_)])
+        ));
+
+        let (result, _, _) = analyze_source(
+            "module Main
+            enum Animal { Dog(String), Cat }
+            enum Shape { Circle(Number) }
+            const a = 1 is Animal.Dog
+            const b = Animal.Cat is Shape.Circle",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 2, "{:#?}", errors);
+    }
+
+    #[test]
+    fn is_narrows_the_subject_in_the_true_branch() {
+        let (result, _, _) = analyze_source(
+            "module Main
+            enum Animal { Dog(String), Cat }
+            const doWoof = (dog: Animal.Dog): String => `woof`
+            const name = (animal: Animal): String => `animal`
+            const speak = (a: Animal): String => if a is Animal.Dog { doWoof(a) } else { name(a) }",
+        );
+        assert!(result.is_ok(), "{:#?}", result.err());
+
+        // the else branch isn't narrowed
+        let (result, _, _) = analyze_source(
+            "module Main
+            enum Animal { Dog(String), Cat }
+            const doWoof = (dog: Animal.Dog): String => `woof`
+            const speak = (a: Animal): String => if a is Animal.Dog { `dog` } else { doWoof(a) }",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1, "{:#?}", errors);
+        assert_eq!(
+            errors[0].diagnostic().labels[0].message,
+            "expected Animal.Dog, found Animal"
+        );
+    }
+
+    #[test]
+    fn variants_are_their_own_types() {
+        let (result, scope_tree, scope_index) = analyze_source(
+            "module Main
+            enum Animal { Dog(String), Cat }
+            const doWoof = (dog: Animal.Dog): String => `woof`
+            const woof = doWoof(Animal.Dog(`Rex`))
+            const rex = Animal.Dog(`Rex`)
+            const alsoWoof = doWoof(rex)
+            const pet: Animal = Animal.Cat
+            const pets = [Animal.Dog(`Rex`), Animal.Cat]
+            const either = if true { Animal.Cat } else { rex }",
+        );
+        let substitution = result.unwrap();
+        let type_of = |name| value_type(&substitution, &scope_tree, scope_index, name).to_string();
+        assert_eq!(type_of("rex"), "Animal.Dog");
+        assert_eq!(type_of("pets"), "Animal[]");
+        assert_eq!(type_of("either"), "Animal");
+
+        let (result, _, _) = analyze_source(
+            "module Main
+            enum Animal { Dog(String), Cat }
+            const doWoof = (dog: Animal.Dog): String => `woof`
+            const pet: Animal = Animal.Cat
+            const meow = doWoof(Animal.Cat)
+            const huh = doWoof(pet)",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 2, "{:#?}", errors);
+        assert!(errors.iter().all(|error| error.code == "E0101"));
+    }
+
+    #[test]
+    fn a_param_passed_on_as_a_variant_stays_that_variant() {
+        let (result, _, _) = analyze_source(
+            "module Main
+            enum Animal { Dog, Cat }
+            const woof = (dog: Animal.Dog): String => `woof`
+            const g = (x) => woof(x)
+            const ok = g(Animal.Dog)
+            const bad = g(Animal.Cat)",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1, "{:#?}", errors);
+        assert_eq!(
+            errors[0].diagnostic().labels[0].message,
+            "expected Animal.Dog, found Animal.Cat"
+        );
+
+        let (result, scope_tree, scope_index) = analyze_source(
+            "module Main
+            enum Animal { Dog, Cat }
+            const woof = (dog: Animal.Dog): String => `woof`
+            const g = (x) => woof(x)
+            const ok = g(Animal.Dog)",
+        );
+        let substitution = result.unwrap();
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "g").to_string(),
+            "(Animal.Dog) => String"
+        );
+    }
+
+    #[test]
+    fn each_array_literal_gets_its_own_element_type() {
+        let (result, scope_tree, scope_index) = analyze_source(
+            "module Main
+            const numbers = [1, 2]
+            const strings = [`a`]
+            const grid: Number[][] = [numbers, [3]]
+            const first = strings[0]",
+        );
+        let substitution = result.unwrap();
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "numbers"),
+            TypeExpr::Array(Box::new(TypeExpr::Number))
+        );
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "first"),
+            TypeExpr::String
+        );
+
+        let (result, _, _) = analyze_source(
+            "module Main
+            const mixed = [1, `two`]
+            const strings = [`a`]
+            const bad = strings[`zero`]
+            const n = 1
+            const notArray = n[0]",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 3, "{:#?}", errors);
+    }
+
+    #[test]
+    fn generic_types_take_their_type_args() {
+        let (result, scope_tree, scope_index) = analyze_source(
+            "module Main
+            type Box<T> = { value: T }
+            enum Option<T> { Some(T), None }
+            const id = (x) => x
+            const boxed: Box<Number> = { value: 1 }
+            const inner = boxed.value
+            const s = id<String>(`one`)
+            const maybe: Option<Number> = Option.Some<Number>(1)
+            const unwrapped = match maybe {
+              Option.Some(n) -> n
+              Option.None -> 0
+            }",
+        );
+        let substitution = result.unwrap();
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "inner"),
+            TypeExpr::Number
+        );
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "s"),
+            TypeExpr::String
+        );
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "unwrapped"),
+            TypeExpr::Number
+        );
+
+        let (result, _, _) = analyze_source(
+            "module Main
+            type Box<T> = { value: T }
+            enum Option<T> { Some(T), None }
+            const id = (x) => x
+            const wrongValue: Box<String> = { value: 1 }
+            const wrongArg = id<String>(1)
+            const wrongOption: Option<String> = Option.Some(1)",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 3, "{:#?}", errors);
+        assert!(errors.iter().all(|error| error.code == "E0101"));
+    }
+
+    #[test]
+    fn type_arg_counts_are_checked() {
+        let (result, _, _) = analyze_source(
+            "module Main
+            type Box<T> = { value: T }
+            enum Option<T> { Some(T), None }
+            const id = (x) => x
+            const tooMany: Box<Number, String> = { value: 1 }
+            const notGeneric = id<Number, String>(1)
+            const tooManyForEnum: Option<Number, String> = Option.None
+            const tooFew: Box = { value: 1 }
+            const tooFewForEnum: Option = Option.None
+            const tooFewInParam = (box: Box): Number => 1",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 6, "{:#?}", errors);
+        assert!(errors.iter().all(|error| error.code == "E0108"));
+
+        // named literals of generic types infer their args
+        let (result, _, _) = analyze_source(
+            "module Main
+            type Box<T> = { value: T }
+            const boxed: Box<Number> = Box { value: 1 }",
+        );
+        assert!(result.is_ok(), "{:#?}", result.err());
+    }
+
+    #[test]
+    fn opaque_types_only_unify_with_themselves() {
+        let (result, scope_tree, scope_index) = analyze_source(
+            "module Main
+            opaque type EmailAddress = String
+            type Name = String
+            const email = EmailAddress(`suzanne@acme.corp`)
+            const raw = EmailAddress.unwrap(email)
+            const name: Name = raw",
+        );
+        let substitution = result.unwrap();
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "email").to_string(),
+            "EmailAddress"
+        );
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "raw"),
+            TypeExpr::String
+        );
+
+        let (result, _, _) = analyze_source(
+            "module Main
+            opaque type EmailAddress = String
+            opaque type Password = String
+            const send = (email: EmailAddress) => email
+            const fromString: EmailAddress = `suzanne@acme.corp`
+            const toString: String = EmailAddress(`suzanne@acme.corp`)
+            const passedString = send(`suzanne@acme.corp`)
+            const otherOpaque: Password = EmailAddress(`suzanne@acme.corp`)",
+        );
+        let errors = result.unwrap_err();
+        let codes: Vec<&str> = errors.iter().map(|error| error.code).collect();
+        assert_eq!(codes, vec!["E0109", "E0109", "E0109", "E0101"]);
+        assert_eq!(
+            errors[0].diagnostic().notes,
+            vec!["make one with EmailAddress(...) and get the value back with EmailAddress.unwrap(...)"]
+        );
+    }
+
+    #[test]
+    fn function_type_annotations_unify_with_lambdas() {
+        let (result, scope_tree, scope_index) = analyze_source(
+            "module Main
+            type Formatter = (Number) => String
+            const apply = (f: (Number) => String, x: Number) => f(x)
+            const format: Formatter = (n) => `${n}`
+            const out = apply((n) => `${n}`, 1)",
+        );
+        let substitution = result.unwrap();
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "out"),
+            TypeExpr::String
+        );
+
+        let (result, _, _) = analyze_source(
+            "module Main
+            const apply = (f: (Number) => String, x: Number) => f(x)
+            const wrongReturn = apply((n) => n, 1)
+            const wrongArity = apply((a, b) => `${a}`, 1)",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 2, "{:#?}", errors);
+        assert_eq!(errors[1].code, "E0105");
+    }
+
+    #[test]
+    fn types_are_inferred_through_pipes() {
+        let (result, scope_tree, scope_index) = analyze_source(
+            "module Main
+            const add = (a: Number, b: Number) => a + b
+            const describe = (n: Number) => `${n}`
+            const total = 1
+              |> add(2)
+              |> describe",
+        );
+
+        let substitution = result.unwrap();
+        assert_eq!(
+            value_type(&substitution, &scope_tree, scope_index, "total"),
+            TypeExpr::String
+        );
+
+        let (result, _, _) = analyze_source(
+            "module Main
+            const add = (a: Number, b: Number) => a + b
+            const bad = `one` |> add(2)",
+        );
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1, "{:#?}", errors);
+        assert_eq!(errors[0].code, "E0101");
+    }
+
+    #[test]
+    fn template_strings_only_interpolate_printable_values() {
+        let (result, _, _) = analyze_source(
+            "module Main
+            const f = (x: Number) => x
+            const ok: String = `${f(1)} and ${`nested ${true}`}`
+            const bad = `f is ${f}`",
+        );
+
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1, "{:#?}", errors);
+        assert_eq!(errors[0].code, "E0107");
+    }
+
+    #[test]
+    fn mismatch_diagnostic_points_at_the_annotation() {
+        let (result, _, _) = analyze_source(
+            "module Main
+            const user: { name: String } = { name: 2 }",
+        );
+
+        let errors = result.unwrap_err();
+        let diagnostic = errors[0].diagnostic();
+        assert_eq!(diagnostic.code, Some("E0101"));
+        assert_eq!(
+            diagnostic.labels[0].message,
+            "expected String, found Number"
+        );
+        assert!(!diagnostic.labels[1].primary);
+    }
+
+    #[test]
+    fn diagnostics_show_solved_types() {
+        let (result, _, _) = analyze_source(
+            "module Main
+            const street = `Main St`
+            const u = { name: `Ann`, address: street }
+            const n = u.nope
+            const f = (x, y) => y
+            const g = f(1)",
+        );
+
+        let errors = result.unwrap_err();
+        let labels: Vec<String> = errors
+            .iter()
+            .map(|error| error.diagnostic().labels[0].message.clone())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "{ name: String, address: String } has no such member",
+                "expected (a, b) => b, found (Number) => c",
+            ],
+        );
+    }
+
+    #[test]
+    fn undefined_names_are_collected_as_errors() {
+        let source = "module Main
+            const a = b + 1
+            const c = a";
+        let tokens = Lexer::new(source.to_string()).tokenize();
+        let program = Parser::new(tokens).parse().unwrap();
+        let mut scope_tree = ScopeTree::new(Arc::new(RwLock::new(ModuleMap::new())));
+        let program = scope_tree.bind_program(program).unwrap();
+        let mut collector = ConstraintCollector::new(&mut scope_tree);
+        collector.collect_program(program);
+
+        assert!(matches!(
+            collector.errors.as_slice(),
+            [CompilerError::UndefinedName { name, .. }] if name == "b"
+        ));
+        // the unknown name is poisoned so it doesn't cause type errors as well
+        let constraints = collector.constraints;
+        assert!(solve(constraints, &scope_tree).is_ok());
+    }
+
+    #[test]
+    fn patterns_must_bind_every_payload_value() {
+        let source = "module Main
+            enum Animal { Dog(Number), Cat }
+            const describe = (a: Animal): String => match (a) {
+              Animal.Dog -> `dog`
+              Animal.Cat(c) -> `cat`
+            }";
+        let tokens = Lexer::new(source.to_string()).tokenize();
+        let program = Parser::new(tokens).parse().unwrap();
+        let mut scope_tree = ScopeTree::new(Arc::new(RwLock::new(ModuleMap::new())));
+        let program = scope_tree.bind_program(program).unwrap();
+        let mut collector = ConstraintCollector::new(&mut scope_tree);
+        collector.collect_program(program);
+
+        let diagnostics: Vec<Diagnostic> = collector
+            .errors
+            .iter()
+            .flat_map(|error| error.diagnostics())
+            .collect();
+        assert_eq!(diagnostics.len(), 2, "{:#?}", diagnostics);
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.code == Some("E0110")));
+        assert_eq!(
+            diagnostics[0].message,
+            "Animal.Dog has 1 payload values but the pattern binds 0"
+        );
+        // the label points at the pattern, not the whole clause
+        let pattern_start = source.find("Animal.Dog ->").unwrap();
+        let label = &diagnostics[0].labels[0];
+        assert_eq!(
+            (label.span.start, label.span.end),
+            (pattern_start, pattern_start + "Animal.Dog".len())
+        );
     }
 }
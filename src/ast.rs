@@ -1,3 +1,50 @@
+use core::fmt;
+
+/**
+ * Byte range of a node within a source file. The file id is the index of the
+ * module in the compiler's ModuleMap.
+ *
+ * Spans never take part in equality checks, two nodes that only differ by
+ * where they were written are considered the same node.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Span {
+    pub file_id: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file_id: usize, start: usize, end: usize) -> Self {
+        Span {
+            file_id,
+            start,
+            end,
+        }
+    }
+
+    /** span covering both self and other */
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            file_id: self.file_id,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub module_dec: ModuleDec,
@@ -10,6 +57,7 @@ pub struct Program {
 pub struct ModuleDec {
     pub name: ModuleName,
     pub exports: Vec<MixedIdentifier>,
+    pub span: Span,
 }
 
 pub type ModuleName = Vec<String>;
@@ -18,12 +66,14 @@ pub type ModuleName = Vec<String>;
 pub struct PackageImport {
     pub package_name: ModuleName,
     pub aliased_name: Option<String>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ExternPackage {
     pub package_name: String,
     pub definitions: Vec<ExternMember>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -33,11 +83,15 @@ pub enum ExternMember {
         external_name: String,
         parameters: Vec<FunctionParameter>,
         return_type: TypeExpr,
+        span: Span,
     },
+    /** not produced by the parser yet */
+    #[allow(dead_code)]
     Variable {
         local_name: Identifier,
         external_name: String,
         value_type: TypeExpr,
+        span: Span,
     },
 }
 
@@ -48,6 +102,8 @@ pub enum TopStatement {
     Expr(Expr),
     EnumDec(EnumDec),
     ExternDec(ExternPackage),
+    /** statement that failed to parse */
+    Error(Span),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -65,9 +121,13 @@ pub enum MixedIdentifier {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConstDec {
+    /** text of the `///` comments right before it */
+    pub doc: Option<String>,
     pub identifier: Identifier,
     pub type_annotation: Option<TypeExpr>,
+    pub type_annotation_span: Option<Span>,
     pub value: Box<Expr>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -79,14 +139,21 @@ pub struct Identifier {
 pub struct FunctionParameter {
     pub identifier: Identifier,
     pub type_expr: Option<TypeExpr>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TypeExpr {
     TypeRef(TypeIdentifier),
+    /** a generic type and its type args, written `Box<Number>` */
+    GenericRef(TypeIdentifier, Vec<TypeExpr>),
     Record(Vec<RecordTypeMemeber>),
+    /** element type, written `T[]` */
+    Array(Box<TypeExpr>),
     EnumDec(EnumDec),
     InferenceRequired(Option<TypeIdentifier>),
+    /** not produced by the parser yet */
+    #[allow(dead_code)]
     DotCall(Box<TypeExpr>, Identifier),
     String,
     Number,
@@ -107,25 +174,110 @@ pub enum TypeExpr {
         package_name: String,
         members: Vec<ExternMember>,
     },
+    /** an opaque type and its underlying type, it only unifies with itself */
+    Opaque(TypeIdentifier, Box<TypeExpr>),
+    /**
+     * one variant of an enum, e.g. Animal.Dog, and the enum's type. It fits
+     * wherever the enum does, but not the other way around
+     */
+    Variant(Box<TypeExpr>, TypeIdentifier),
+    /** stands in for a type that failed to check, unifies with anything */
+    Error,
+    /** type generalized over some type vars, every reference gets fresh vars */
+    Scheme {
+        type_vars: Vec<TypeIdentifier>,
+        type_expr: Box<TypeExpr>,
+    },
+}
+
+fn join_types(types: &[TypeExpr]) -> String {
+    types
+        .iter()
+        .map(|type_expr| type_expr.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/** how types are written in fyg source, used for error messages */
+impl fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeExpr::TypeRef(type_identifier) => write!(f, "{}", type_identifier.name.join(".")),
+            TypeExpr::GenericRef(type_identifier, type_args) => write!(
+                f,
+                "{}<{}>",
+                type_identifier.name.join("."),
+                join_types(type_args)
+            ),
+            TypeExpr::Record(members) => {
+                let members: Vec<String> = members
+                    .iter()
+                    .map(|member| format!("{}: {}", member.identifier.name, member.type_expr))
+                    .collect();
+                write!(f, "{{ {} }}", members.join(", "))
+            }
+            TypeExpr::EnumDec(enum_dec) => write!(f, "{}", enum_dec.identifier.name.join(".")),
+            TypeExpr::InferenceRequired(Some(type_var)) => {
+                write!(f, "{}", type_var.name.join("."))
+            }
+            TypeExpr::Array(element_type) => match element_type.as_ref() {
+                // (A) => B[] would read as a function returning an array
+                TypeExpr::FunctionDefinition { .. } => write!(f, "({})[]", element_type),
+                _ => write!(f, "{}[]", element_type),
+            },
+            TypeExpr::InferenceRequired(None) => write!(f, "_"),
+            TypeExpr::DotCall(type_expr, member) => write!(f, "{}.{}", type_expr, member.name),
+            TypeExpr::String => write!(f, "String"),
+            TypeExpr::Number => write!(f, "Number"),
+            TypeExpr::Boolean => write!(f, "Boolean"),
+            TypeExpr::Void => write!(f, "Void"),
+            TypeExpr::ImportRef(name, _) => write!(f, "{}", name),
+            TypeExpr::FunctionDefinition {
+                parameters,
+                return_type,
+                ..
+            } => write!(f, "({}) => {}", join_types(parameters), return_type),
+            TypeExpr::FunctionCall { args, callee, .. } => {
+                write!(f, "{}({})", callee, join_types(args))
+            }
+            TypeExpr::ExternPackage { package_name, .. } => write!(f, "extern {}", package_name),
+            TypeExpr::Opaque(type_identifier, _) => {
+                write!(f, "{}", type_identifier.name.join("."))
+            }
+            TypeExpr::Variant(_, variant) => write!(f, "{}", variant.name.join(".")),
+            TypeExpr::Error => write!(f, "{{error}}"),
+            TypeExpr::Scheme {
+                type_vars,
+                type_expr,
+            } => {
+                let type_vars: Vec<String> = type_vars.iter().map(|v| v.name.join(".")).collect();
+                write!(f, "<{}>{}", type_vars.join(", "), type_expr)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct RecordTypeMemeber {
     pub identifier: Identifier,
     pub type_expr: TypeExpr,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct EnumDec {
+    pub doc: Option<String>,
     pub identifier: TypeIdentifier,
     pub type_vars: Vec<TypeIdentifier>,
     pub variants: Vec<EnumVariant>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct EnumVariant {
     pub name: TypeIdentifier,
     pub params: Vec<TypeExpr>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -135,16 +287,34 @@ pub struct TypeIdentifier {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct TypeDec {
+    pub doc: Option<String>,
     pub identifier: TypeIdentifier,
     pub type_vars: Vec<TypeIdentifier>,
     pub type_val: TypeExpr,
+    /** declared with `opaque type`, otherwise it's a transparent alias */
+    pub opaque: bool,
     pub scope: Option<usize>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expr {
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
     Number(String),
     String(String),
+    /** `Hello ${name}!` */
+    Template(Vec<TemplatePart>),
     Boolean(bool),
     FunctionDefinition {
         parameters: Vec<FunctionParameter>,
@@ -153,35 +323,68 @@ pub enum Expr {
         scope: Option<usize>,
         identifier: Option<Identifier>,
     },
-    ValueReference(MixedIdentifier),
-    Record(Option<TypeIdentifier>, Vec<ObjectMember>),
+    /** the name and its type at this use, an instance if it's generic (set when bound) */
+    ValueReference(MixedIdentifier, Option<TypeExpr>),
+    /** optional type name, members and the type of the literal (set when bound) */
+    Record(Option<TypeIdentifier>, Vec<ObjectMember>, Option<TypeExpr>),
+    /** element type, a fresh type var for each literal once bound, and the elements */
     Array(TypeExpr, Vec<Expr>),
+    /**
+     * `xs[i]`, the index is truncated toward zero when the program runs, so
+     * `xs[1.5]` is `xs[1]`. Going out of bounds panics, same as Go. The
+     * element type is set when bound
+     */
+    Index(Box<Expr>, Box<Expr>, Option<TypeExpr>),
     BlockExpression(Vec<BlockStatement>, Option<usize>),
+    /** not produced by the parser yet */
+    #[allow(dead_code)]
     Void,
     Binary(Box<Expr>, BinaryOp, Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    /**
+     * `animal is Animal.Dog`, negated for `!is`. When it's an if condition
+     * the subject is narrowed to the variant in the true branch
+     */
+    Is {
+        subject: Box<Expr>,
+        variant: TypeIdentifier,
+        negated: bool,
+    },
     DotCall(Box<Expr>, Identifier),
     FunctionCall {
         callee: Box<Expr>,
         args: Vec<Expr>,
-        generic_args: Vec<Expr>,
+        /** explicit type args, e.g. the Number in `identity<Number>(1)` */
+        generic_args: Vec<TypeExpr>,
     },
-    Match(Box<Expr>, Vec<MatchClause>),
-    IfElse(Box<Expr>, Box<Expr>, Box<Expr>),
+    /** subject, clauses and the type every clause evaluates to (set when bound) */
+    Match(Box<Expr>, Vec<MatchClause>, Option<TypeExpr>),
+    /** condition, true branch, else branch and the type both branches evaluate to */
+    IfElse(Box<Expr>, Box<Expr>, Box<Expr>, Option<TypeExpr>),
+    /** expression that failed to parse */
+    Error,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct ObjectExpr {}
+pub enum TemplatePart {
+    Text(String),
+    Expr(Expr),
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ObjectMember {
     pub key: Identifier,
     pub value: Expr,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MatchClause {
     pub pattern: Pattern,
+    pub pattern_span: Span,
     pub body: Expr,
+    pub scope: Option<usize>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -190,6 +393,8 @@ pub enum Pattern {
     Number(String),
     Boolean(bool),
     ValueRef(Identifier),
+    /** enum variant and the names its payload values are bound to */
+    Variant(TypeIdentifier, Vec<Identifier>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -204,4 +409,15 @@ pub enum BinaryOp {
     GreaterOrEqual,
     LessThan,
     LessOrEqual,
+    Power,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnaryOp {
+    /** -x */
+    Negate,
+    /** !x */
+    Not,
 }
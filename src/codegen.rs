@@ -1,9 +1,11 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap};
 
 use crate::{
+    analyze::Substitution,
     ast::{
-        BinaryOp, BlockStatement, ConstDec, Expr, ExternMember, Identifier, MixedIdentifier,
-        Program, TopStatement, TypeExpr,
+        BinaryOp, BlockStatement, ConstDec, EnumDec, Expr, ExprKind, ExternMember,
+        FunctionParameter, Identifier, MatchClause, MixedIdentifier, ObjectMember, Pattern,
+        Program, TemplatePart, TopStatement, TypeDec, TypeExpr, TypeIdentifier, UnaryOp,
     },
     scope::ScopeTree,
 };
@@ -16,11 +18,50 @@ pub struct CodeGenerator {
     main_stmts: Vec<String>,
     program: Program,
     scope_tree: ScopeTree,
+    substitution: Substitution,
     import_map: HashMap<String, String>,
+    /** Go std packages the generated code calls into, e.g. fmt for template strings */
+    std_imports: RefCell<Vec<&'static str>>,
+    /**
+     * Go types for the type vars of the generic being generated, a type
+     * param like T or a concrete type when monomorphizing
+     */
+    type_params: RefCell<HashMap<String, String>>,
+    /** top level functions that were generalized, with the vars of their scheme */
+    generic_fns: HashMap<String, (ConstDec, Vec<TypeIdentifier>)>,
+    monomorphize: bool,
+    /** generic functions and the Go type args each copy is needed for */
+    instances: RefCell<Vec<(String, Vec<String>)>>,
+    /** the same for generic enums, when monomorphizing */
+    type_instances: RefCell<Vec<(String, Vec<String>)>>,
+}
+
+/** Go type param names, T, U, V, W and then T4, T5... */
+fn type_param_name(index: usize) -> String {
+    match ["T", "U", "V", "W"].get(index) {
+        Some(name) => name.to_string(),
+        None => format!("T{}", index),
+    }
+}
+
+/** Go type args as part of a name, e.g. float64_arr_string for [float64, []string] */
+fn mangle_type_args(type_args: &[String]) -> String {
+    let mangled: Vec<String> = type_args
+        .iter()
+        .map(|type_arg| {
+            let type_arg = type_arg.replace("[]", "arr_");
+            let words: Vec<&str> = type_arg
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .collect();
+            words.join("_")
+        })
+        .collect();
+    mangled.join("_")
 }
 
 impl CodeGenerator {
-    pub fn new(program: Program, scope_tree: ScopeTree) -> Self {
+    pub fn new(program: Program, scope_tree: ScopeTree, substitution: Substitution) -> Self {
         CodeGenerator {
             package_name: program.module_dec.name.join("").to_lowercase(),
             imports: Vec::new(),
@@ -28,10 +69,27 @@ impl CodeGenerator {
             main_stmts: Vec::new(),
             program,
             scope_tree,
+            substitution,
             import_map: HashMap::new(),
+            std_imports: RefCell::new(Vec::new()),
+            type_params: RefCell::new(HashMap::new()),
+            generic_fns: HashMap::new(),
+            monomorphize: false,
+            instances: RefCell::new(Vec::new()),
+            type_instances: RefCell::new(Vec::new()),
         }
     }
 
+    /**
+     * Generic functions and enums are copied for each set of type args
+     * they're used with instead of becoming Go generics, for what Go's can't
+     * express
+     */
+    pub fn monomorphize(mut self, monomorphize: bool) -> Self {
+        self.monomorphize = monomorphize;
+        self
+    }
+
     pub fn generate_go(&mut self) -> String {
         if let Some(program_scope_index) = self.program.scope {
             for import in &self.program.imports {
@@ -56,11 +114,15 @@ impl CodeGenerator {
                 );
                 self.imports.push(go_package_name)
             }
+            self.generic_fns = self.find_generic_fns(program_scope_index);
             for statement in &self.program.statements {
                 match statement {
                     TopStatement::ConstDec(const_dec) => {
-                        self.top_level_stmts
-                            .push(self.generate_const_dec(const_dec, program_scope_index));
+                        let const_dec = self.generate_const_dec(const_dec, program_scope_index);
+                        // generic functions are generated per instance when monomorphizing
+                        if !const_dec.is_empty() {
+                            self.top_level_stmts.push(const_dec);
+                        }
                     }
                     TopStatement::Expr(expr) => {
                         self.main_stmts
@@ -69,13 +131,84 @@ impl CodeGenerator {
                     TopStatement::ExternDec(extern_dec) => {
                         self.imports.push(extern_dec.package_name.clone());
                     }
-                    _ => {
-                        panic!("Not implemented {:#?}", statement);
+                    // uses of generic type decs are written as the type they
+                    // stand for, so there's nothing to copy when monomorphizing
+                    TopStatement::TypeDec(type_dec)
+                        if self.monomorphize && !type_dec.type_vars.is_empty() => {}
+                    TopStatement::TypeDec(type_dec) => {
+                        self.top_level_stmts.push(self.generate_type_dec(type_dec));
+                    }
+                    // generated per instance too
+                    TopStatement::EnumDec(enum_dec)
+                        if self.monomorphize && !enum_dec.type_vars.is_empty() => {}
+                    TopStatement::EnumDec(enum_dec) => {
+                        self.top_level_stmts.push(self.generate_enum_dec(
+                            enum_dec,
+                            program_scope_index,
+                            None,
+                        ));
+                    }
+                    TopStatement::Error(span) => {
+                        panic!("statement at {} failed to parse, can't generate it", span)
                     }
                 }
             }
         }
 
+        // copies can use other generic functions and enums, which might need more copies
+        let (mut generated_fns, mut generated_types) = (0, 0);
+        loop {
+            let program_scope_index = self.program.scope.expect("program scope");
+            let instance = self.instances.borrow().get(generated_fns).cloned();
+            if let Some((fn_name, type_args)) = instance {
+                generated_fns += 1;
+                let (const_dec, type_vars) = self.generic_fns[&fn_name].clone();
+                let go_name = self.instance_name(&fn_name, &type_args);
+                let type_params = type_vars
+                    .iter()
+                    .map(|type_var| type_var.name.join("."))
+                    .zip(type_args)
+                    .collect();
+                self.top_level_stmts.push(self.generate_generic_fn(
+                    &const_dec,
+                    &go_name,
+                    type_params,
+                    program_scope_index,
+                ));
+                continue;
+            }
+            let type_instance = self.type_instances.borrow().get(generated_types).cloned();
+            let Some((type_name, type_args)) = type_instance else {
+                break;
+            };
+            generated_types += 1;
+            let enum_dec = self
+                .program
+                .statements
+                .iter()
+                .find_map(|statement| match statement {
+                    TopStatement::EnumDec(enum_dec)
+                        if enum_dec.identifier.name.join(".") == type_name =>
+                    {
+                        Some(enum_dec.clone())
+                    }
+                    _ => None,
+                });
+            if let Some(enum_dec) = enum_dec {
+                self.top_level_stmts.push(self.generate_enum_dec(
+                    &enum_dec,
+                    program_scope_index,
+                    Some(&type_args),
+                ));
+            }
+        }
+
+        for package in self.std_imports.take() {
+            if !self.imports.iter().any(|import| import == package) {
+                self.imports.push(package.to_string());
+            }
+        }
+
         let mut final_source = format!("package {}\n\n", self.package_name);
 
         let imports_source = match self.imports.len() {
@@ -121,80 +254,48 @@ impl CodeGenerator {
             .find_value_symbol(scope_index, &const_dec.identifier.name)
             .expect("type symbol should exist");
         let const_value = *const_dec.value.clone();
-        match const_value {
-            Expr::FunctionDefinition {
-                parameters,
-                return_type,
-                body,
-                scope: Some(fn_scope),
-                identifier,
-            } => {
-                let params: Vec<String> = parameters
+        match const_value.kind {
+            ExprKind::FunctionDefinition { .. }
+                if Some(scope_index) == self.program.scope
+                    && self.generic_fns.contains_key(&const_dec.identifier.name) =>
+            {
+                if self.monomorphize {
+                    return String::new();
+                }
+                let (_, type_vars) = &self.generic_fns[&const_dec.identifier.name];
+                let type_params: HashMap<String, String> = type_vars
                     .iter()
-                    .map(|p| {
-                        let resolved_param_type = self.scope_tree.resolve_type(
-                            p.type_expr.clone().expect("type expr value"),
-                            scope_index,
-                        );
-
-                        format!(
-                            "{} {}",
-                            self.generate_go_identifier(p.identifier.clone()),
-                            self.primitive_type_conversion(resolved_param_type),
-                        )
-                    })
+                    .enumerate()
+                    .map(|(index, type_var)| (type_var.name.join("."), type_param_name(index)))
                     .collect();
-                let return_type = self
-                    .scope_tree
-                    .resolve_type(return_type.expect("return_type"), scope_index);
-                let rendered_body = match *body {
-                    Expr::BlockExpression(exprs, Some(block_scope)) => {
-                        let indent = self.indent(block_scope);
-                        println!("indent: '{}'", indent);
-                        let exprs_source = exprs
-                            .iter()
-                            .map(|stmt| match stmt {
-                                BlockStatement::Expr(expr) => {
-                                    format!("{}{}", indent, self.generate_expr(expr, block_scope))
-                                }
-                                BlockStatement::Return(expr) => {
-                                    format!(
-                                        "{}return {}",
-                                        indent,
-                                        self.generate_expr(expr, block_scope)
-                                    )
-                                }
-                                BlockStatement::ConstDec(const_dec) => {
-                                    format!(
-                                        "{}{}",
-                                        indent,
-                                        self.generate_const_dec(const_dec, block_scope)
-                                    )
-                                }
-                            })
-                            .collect::<Vec<String>>()
-                            .join("\n");
-                        exprs_source
-                    }
-                    _ => {
-                        let indent = self.indent(scope_index);
-                        format!(
-                            "  {}return {};",
-                            indent,
-                            self.generate_expr(&body, scope_index)
-                        )
-                    }
-                };
-                format!(
-                    "func {}({}) {} {{\n{}\n}}",
+                let go_name = format!(
+                    "{}[{}]",
                     self.generate_go_identifier(const_dec.identifier.clone()),
-                    params.join(", "),
-                    self.primitive_type_conversion(return_type),
-                    rendered_body,
-                )
+                    (0..type_vars.len())
+                        .map(|index| format!("{} any", type_param_name(index)))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                );
+                self.generate_generic_fn(const_dec, &go_name, type_params, scope_index)
             }
+            ExprKind::FunctionDefinition {
+                parameters,
+                return_type,
+                body,
+                scope: Some(fn_scope),
+                ..
+            } => self.generate_fn(
+                &self.generate_go_identifier(const_dec.identifier.clone()),
+                &parameters,
+                return_type,
+                &body,
+                fn_scope,
+                scope_index,
+            ),
             _ => {
-                let const_type = self.primitive_type_conversion(value_symbol.type_expr);
+                let const_type = self.primitive_type_conversion(
+                    self.resolve_type(value_symbol.type_expr, scope_index),
+                );
                 format!(
                     "var {} {} = {};\n",
                     self.generate_go_identifier(const_dec.identifier.clone()),
@@ -205,13 +306,722 @@ impl CodeGenerator {
         }
     }
 
-    fn primitive_type_conversion(&self, type_expr: TypeExpr) -> &str {
+    /** top level consts whose function got generalized, they become Go generics */
+    fn find_generic_fns(
+        &self,
+        scope_index: usize,
+    ) -> HashMap<String, (ConstDec, Vec<TypeIdentifier>)> {
+        let mut generic_fns = HashMap::new();
+        for statement in &self.program.statements {
+            let TopStatement::ConstDec(const_dec) = statement else {
+                continue;
+            };
+            if !matches!(const_dec.value.kind, ExprKind::FunctionDefinition { .. }) {
+                continue;
+            }
+            let Some(value_symbol) = self
+                .scope_tree
+                .find_value_symbol(scope_index, &const_dec.identifier.name)
+            else {
+                continue;
+            };
+            if let TypeExpr::Scheme { type_vars, .. } =
+                self.resolve_type(value_symbol.type_expr, scope_index)
+            {
+                generic_fns.insert(
+                    const_dec.identifier.name.clone(),
+                    (const_dec.clone(), type_vars),
+                );
+            }
+        }
+        generic_fns
+    }
+
+    /** a generic function with its type vars written as the given Go types */
+    fn generate_generic_fn(
+        &self,
+        const_dec: &ConstDec,
+        go_name: &str,
+        type_params: HashMap<String, String>,
+        scope_index: usize,
+    ) -> String {
+        let ExprKind::FunctionDefinition {
+            parameters,
+            return_type,
+            body,
+            scope: Some(fn_scope),
+            ..
+        } = &const_dec.value.kind
+        else {
+            panic!("{} should be a bound function", const_dec.identifier.name);
+        };
+        let previous = self.type_params.replace(type_params);
+        let go_fn = self.generate_fn(
+            go_name,
+            parameters,
+            return_type.clone(),
+            body,
+            *fn_scope,
+            scope_index,
+        );
+        self.type_params.replace(previous);
+        go_fn
+    }
+
+    /**
+     * Go type args for one use of a generic function, worked out by lining
+     * its scheme up with the type at the use. None for other values
+     */
+    fn fn_type_args(
+        &self,
+        name: &str,
+        use_type: &Option<TypeExpr>,
+        scope_index: usize,
+    ) -> Option<Vec<String>> {
+        let (_, type_vars) = self.generic_fns.get(name)?;
+        let program_scope_index = self.program.scope?;
+        let value_symbol = self
+            .scope_tree
+            .find_value_symbol(program_scope_index, name)?;
+        let TypeExpr::Scheme { type_expr, .. } =
+            self.resolve_type(value_symbol.type_expr, program_scope_index)
+        else {
+            return None;
+        };
+        let mut bound = HashMap::new();
+        bind_type_params(
+            &type_expr,
+            &self.resolve_type(use_type.clone()?, scope_index),
+            &mut bound,
+        );
+        Some(
+            type_vars
+                .iter()
+                .map(|type_var| match bound.get(&type_var.name.join(".")) {
+                    Some(type_arg) => self.primitive_type_conversion(type_arg.clone()),
+                    None => "any".to_string(),
+                })
+                .collect(),
+        )
+    }
+
+    /** name of the copy of a generic function for these type args, e.g. identity_float64 */
+    fn instance_name(&self, fn_name: &str, type_args: &[String]) -> String {
+        format!(
+            "{}_{}",
+            self.generate_go_identifier(Identifier {
+                name: fn_name.to_string()
+            }),
+            mangle_type_args(type_args)
+        )
+    }
+
+    /**
+     * How a generic type's args are written after its name, [float64], or
+     * _float64 for the copy made for them when monomorphizing
+     */
+    fn type_args_suffix(&self, type_identifier: &TypeIdentifier, type_args: Vec<String>) -> String {
+        if !self.monomorphize {
+            return format!("[{}]", type_args.join(", "));
+        }
+        let suffix = format!("_{}", mangle_type_args(&type_args));
+        let instance = (type_identifier.name.join("."), type_args);
+        let mut type_instances = self.type_instances.borrow_mut();
+        if !type_instances.contains(&instance) {
+            type_instances.push(instance);
+        }
+        suffix
+    }
+
+    /**
+     * Go type args of a generic enum's type, e.g. [float64] for Option<Number>,
+     * or _float64 when monomorphizing
+     */
+    fn go_type_args(&self, type_expr: TypeExpr, scope_index: usize) -> String {
+        match self.resolve_type(type_expr, scope_index) {
+            // a variant's constructor, the args are on what it returns
+            TypeExpr::FunctionDefinition { return_type, .. } => {
+                self.go_type_args(*return_type, scope_index)
+            }
+            TypeExpr::Variant(enum_type, _) => self.go_type_args(*enum_type, scope_index),
+            TypeExpr::GenericRef(type_identifier, type_args) if !type_args.is_empty() => {
+                let type_args: Vec<String> = type_args
+                    .into_iter()
+                    .map(|type_arg| self.primitive_type_conversion(type_arg))
+                    .collect();
+                self.type_args_suffix(&type_identifier, type_args)
+            }
+            _ => String::new(),
+        }
+    }
+
+    /** the type of an expression, when it's kept in the tree */
+    fn expr_type(&self, expr: &Expr, scope_index: usize) -> Option<TypeExpr> {
+        match &expr.kind {
+            ExprKind::ValueReference(_, use_type) => use_type.clone(),
+            ExprKind::FunctionCall { callee, .. } => {
+                match self.resolve_type(self.expr_type(callee, scope_index)?, scope_index) {
+                    TypeExpr::FunctionDefinition { return_type, .. } => Some(*return_type),
+                    _ => None,
+                }
+            }
+            ExprKind::Record(_, _, expr_type)
+            | ExprKind::Match(_, _, expr_type)
+            | ExprKind::IfElse(_, _, _, expr_type)
+            | ExprKind::Index(_, _, expr_type) => expr_type.clone(),
+            _ => None,
+        }
+    }
+
+    /**
+     * Enums become an interface with an unexported marker method, and each
+     * variant is a struct implementing it. Payload values are positional
+     * fields named _0, _1...
+     *
+     * With type args it's the monomorphized copy of a generic enum, every
+     * name gets the mangled args, e.g. Option_Some_float64
+     */
+    fn generate_enum_dec(
+        &self,
+        enum_dec: &EnumDec,
+        scope_index: usize,
+        type_args: Option<&[String]>,
+    ) -> String {
+        let name_suffix = type_args
+            .map(|type_args| format!("_{}", mangle_type_args(type_args)))
+            .unwrap_or_default();
+        let enum_name = format!(
+            "{}{}",
+            self.generate_type_name(&enum_dec.identifier),
+            name_suffix
+        );
+        let marker = format!("is{}", enum_name);
+        // generic enums are Go generics, or a copy's concrete types, the
+        // variants' payloads refer to the vars bind_enum_dec put in place of
+        // the enum's type vars
+        let type_param_names: Vec<String> = match type_args {
+            Some(type_args) => type_args.to_vec(),
+            None => enum_dec
+                .type_vars
+                .iter()
+                .map(|type_var| self.generate_type_name(type_var))
+                .collect(),
+        };
+        let (type_params_dec, type_params_use) =
+            if type_param_names.is_empty() || type_args.is_some() {
+                (String::new(), String::new())
+            } else {
+                (
+                    format!("[{} any]", type_param_names.join(", ")),
+                    format!("[{}]", type_param_names.join(", ")),
+                )
+            };
+        let previous = self.type_params.replace(
+            self.enum_scheme_vars(enum_dec, scope_index)
+                .into_iter()
+                .zip(type_param_names)
+                .collect(),
+        );
+        let mut decs = vec![format!(
+            "type {}{} interface {{\n\t{}()\n}}",
+            enum_name, type_params_dec, marker
+        )];
+
+        for variant in &enum_dec.variants {
+            let mut variant_identifier = enum_dec.identifier.clone();
+            variant_identifier.name.extend(variant.name.name.clone());
+            let variant_name = format!(
+                "{}{}",
+                self.generate_type_name(&variant_identifier),
+                name_suffix
+            );
+            let fields: Vec<String> = variant
+                .params
+                .iter()
+                .enumerate()
+                .map(|(index, param)| {
+                    let param_type = self.resolve_type(param.clone(), scope_index);
+                    format!(
+                        "\t_{} {}\n",
+                        index,
+                        self.primitive_type_conversion(param_type)
+                    )
+                })
+                .collect();
+            let fields = if fields.is_empty() {
+                "{}".to_string()
+            } else {
+                format!("{{\n{}}}", fields.join(""))
+            };
+            decs.push(format!(
+                "type {}{} struct {}\n\nfunc ({}{}) {}() {{}}",
+                variant_name, type_params_dec, fields, variant_name, type_params_use, marker
+            ));
+        }
+        self.type_params.replace(previous);
+
+        decs.join("\n\n")
+    }
+
+    /** the vars a generic enum's variants are generalized over, in declared order */
+    fn enum_scheme_vars(&self, enum_dec: &EnumDec, scope_index: usize) -> Vec<String> {
+        let Some(variant) = enum_dec.variants.first() else {
+            return Vec::new();
+        };
+        let mut variant_identifier = enum_dec.identifier.clone();
+        variant_identifier.name.extend(variant.name.name.clone());
+        match self
+            .scope_tree
+            .find_value_symbol(scope_index, &variant_identifier.name.join("."))
+            .map(|symbol| symbol.type_expr)
+        {
+            Some(TypeExpr::Scheme { type_vars, .. }) => type_vars
+                .iter()
+                .map(|type_var| type_var.name.join("."))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /** a variant's Go type, with the type args of the enum value it's tested against */
+    fn variant_go_type(
+        &self,
+        variant: &TypeIdentifier,
+        subject: &Expr,
+        scope_index: usize,
+    ) -> String {
+        format!(
+            "{}{}",
+            self.generate_type_name(variant),
+            self.expr_type(subject, scope_index)
+                .map(|subject_type| self.go_type_args(subject_type, scope_index))
+                .unwrap_or_default()
+        )
+    }
+
+    /**
+     * Type decs are Go aliases, records are structural so a User and an
+     * anonymous record with the same members need to be the same Go type.
+     * Opaque types are defined types, so Go keeps them apart from their
+     * underlying type too. Generic ones are defined types as well, generic
+     * aliases need Go 1.24 and uses of them are written as the type they
+     * stand for anyway
+     */
+    fn generate_type_dec(&self, type_dec: &TypeDec) -> String {
+        let type_val = self.primitive_type_conversion(type_dec.type_val.clone());
+        let type_name = self.generate_type_name(&type_dec.identifier);
+        if type_dec.opaque {
+            return format!("type {} {}", type_name, type_val);
+        }
+        if type_dec.type_vars.is_empty() {
+            return format!("type {} = {}", type_name, type_val);
+        }
+        let type_vars: Vec<String> = type_dec
+            .type_vars
+            .iter()
+            .map(|type_var| self.generate_type_name(type_var))
+            .collect();
+        format!(
+            "type {}[{} any] {}",
+            type_name,
+            type_vars.join(", "),
+            type_val
+        )
+    }
+
+    fn generate_record_literal(
+        &self,
+        type_identifier: &Option<TypeIdentifier>,
+        members: &[ObjectMember],
+        record_type: TypeExpr,
+        scope_index: usize,
+    ) -> String {
+        // Go can't infer a generic type's args from a literal, so those use the struct type
+        let is_generic = |type_identifier: &TypeIdentifier| {
+            matches!(
+                self.scope_tree
+                    .find_type_symbol(scope_index, type_identifier.clone())
+                    .map(|symbol| symbol.type_expr),
+                Some(TypeExpr::Scheme { .. })
+            )
+        };
+        let go_type = match type_identifier {
+            Some(type_identifier) if !is_generic(type_identifier) => {
+                self.generate_type_name(type_identifier)
+            }
+            _ => self.primitive_type_conversion(record_type),
+        };
+        let values: Vec<String> = members
+            .iter()
+            .map(|member| {
+                format!(
+                    "{}: {}",
+                    self.generate_go_identifier(member.key.clone()),
+                    self.generate_expr(&member.value, scope_index)
+                )
+            })
+            .collect();
+        format!("{}{{{}}}", go_type, values.join(", "))
+    }
+
+    /** true when the type identifier names a variant, e.g. Animal.Dog */
+    fn is_enum_variant(&self, type_identifier: &TypeIdentifier, scope_index: usize) -> bool {
+        let Some((_, enum_name)) = type_identifier.name.split_last() else {
+            return false;
+        };
+        let enum_identifier = TypeIdentifier {
+            name: enum_name.to_vec(),
+        };
+        !enum_name.is_empty()
+            && matches!(
+                self.scope_tree
+                    .find_type_symbol(scope_index, enum_identifier)
+                    .map(|symbol| symbol.type_expr),
+                Some(TypeExpr::EnumDec(_))
+            )
+    }
+
+    /** the underlying type when the type identifier names an opaque type */
+    fn opaque_type(
+        &self,
+        type_identifier: &TypeIdentifier,
+        scope_index: usize,
+    ) -> Option<TypeExpr> {
+        match self
+            .scope_tree
+            .find_type_symbol(scope_index, type_identifier.clone())
+            .map(|symbol| symbol.type_expr)
+        {
+            Some(TypeExpr::Opaque(_, underlying)) => Some(*underlying),
+            _ => None,
+        }
+    }
+
+    /** the solved type, with the vars nested in functions and records solved too */
+    fn resolve_type(&self, type_expr: TypeExpr, scope_index: usize) -> TypeExpr {
+        self.substitution
+            .apply(type_expr, &self.scope_tree, scope_index)
+    }
+
+    fn generate_type_name(&self, type_identifier: &TypeIdentifier) -> String {
+        type_identifier.name.join("_")
+    }
+
+    /** a named Go function, or a func literal when name is empty */
+    fn generate_fn(
+        &self,
+        name: &str,
+        parameters: &[FunctionParameter],
+        return_type: Option<TypeExpr>,
+        body: &Expr,
+        fn_scope: usize,
+        scope_index: usize,
+    ) -> String {
+        let params: Vec<String> = parameters
+            .iter()
+            .map(|p| {
+                let resolved_param_type =
+                    self.resolve_type(p.type_expr.clone().expect("type expr value"), scope_index);
+
+                format!(
+                    "{} {}",
+                    self.generate_go_identifier(p.identifier.clone()),
+                    self.primitive_type_conversion(resolved_param_type),
+                )
+            })
+            .collect();
+        let return_type = self.resolve_type(return_type.expect("return_type"), scope_index);
+        let returns_value = return_type != TypeExpr::Void;
+        let rendered_body = self.generate_body(body, fn_scope, returns_value);
+        let keyword = if name.is_empty() { "func" } else { "func " };
+        format!(
+            "{}{}({}) {} {{\n{}\n}}",
+            keyword,
+            name,
+            params.join(", "),
+            self.primitive_type_conversion(return_type),
+            rendered_body,
+        )
+    }
+
+    /**
+     * Renders an expression that produces a value as Go statements, a block's
+     * trailing expression becomes the returned value
+     */
+    fn generate_body(&self, body: &Expr, scope_index: usize, returns_value: bool) -> String {
+        match &body.kind {
+            ExprKind::BlockExpression(statements, Some(block_scope)) => {
+                self.generate_block(statements, *block_scope, returns_value)
+            }
+            _ => {
+                let indent = self.indent(scope_index);
+                let expr = self.generate_expr(body, scope_index);
+                if returns_value {
+                    format!("  {}return {}", indent, expr)
+                } else {
+                    format!("  {}{}", indent, expr)
+                }
+            }
+        }
+    }
+
+    fn generate_block(
+        &self,
+        statements: &[BlockStatement],
+        block_scope: usize,
+        returns_value: bool,
+    ) -> String {
+        let indent = self.indent(block_scope);
+        eprintln!("indent: '{}'", indent);
+        statements
+            .iter()
+            .enumerate()
+            .map(|(index, stmt)| match stmt {
+                BlockStatement::Expr(expr) if returns_value && index + 1 == statements.len() => {
+                    format!("{}return {}", indent, self.generate_expr(expr, block_scope))
+                }
+                BlockStatement::Expr(expr) => {
+                    format!("{}{}", indent, self.generate_expr(expr, block_scope))
+                }
+                BlockStatement::Return(expr) => {
+                    format!("{}return {}", indent, self.generate_expr(expr, block_scope))
+                }
+                BlockStatement::ConstDec(const_dec) => {
+                    format!(
+                        "{}{}",
+                        indent,
+                        self.generate_const_dec(const_dec, block_scope)
+                    )
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /**
+     * match is an expression, so the Go switch is wrapped in a func that's
+     * called straight away
+     */
+    fn generate_match(
+        &self,
+        subject: &Expr,
+        clauses: &[MatchClause],
+        match_type: TypeExpr,
+        scope_index: usize,
+    ) -> String {
+        let match_type = self.resolve_type(match_type, scope_index);
+        let returns_value = match_type != TypeExpr::Void;
+        let mut cases = Vec::new();
+        let mut has_default = false;
+        // variants are told apart by their Go type, so enums need a type switch
+        let is_type_switch = clauses
+            .iter()
+            .any(|clause| matches!(clause.pattern, Pattern::Variant(_, _)));
+
+        for clause in clauses {
+            let clause_scope = clause.scope.expect("match clause should have a scope");
+            let body = self.generate_body(&clause.body, clause_scope, returns_value);
+            let case = match &clause.pattern {
+                Pattern::String(string) => self.generate_string_literal(string),
+                Pattern::Number(number) => number.to_string(),
+                Pattern::Boolean(bool) => bool.to_string(),
+                Pattern::Variant(variant, bindings) => {
+                    let bound_payload: Vec<String> = bindings
+                        .iter()
+                        .enumerate()
+                        .map(|(index, binding)| {
+                            let name = self.generate_go_identifier(binding.clone());
+                            format!("{name} := matchSubject._{index}\n_ = {name}\n")
+                        })
+                        .collect();
+                    cases.push(format!(
+                        "case {}:\n_ = matchSubject\n{}{}",
+                        self.variant_go_type(variant, subject, scope_index),
+                        bound_payload.join(""),
+                        body
+                    ));
+                    continue;
+                }
+                Pattern::ValueRef(identifier) => {
+                    let name = self.generate_go_identifier(identifier.clone());
+                    cases.push(format!(
+                        "default:\n{name} := matchSubject\n_ = {name}\n{body}"
+                    ));
+                    // nothing after a catch-all clause can match
+                    has_default = true;
+                    break;
+                }
+            };
+            cases.push(format!("case {}:\n{}", case, body));
+        }
+
+        let no_match = if returns_value && !has_default {
+            "\npanic(\"no match clause matched\")"
+        } else {
+            ""
+        };
+        let subject = self.generate_expr(subject, scope_index);
+        let switch = if is_type_switch {
+            // any() so a subject narrowed by is can still be switched on
+            format!("switch matchSubject := any({}).(type)", subject)
+        } else {
+            format!("switch matchSubject := {}; matchSubject", subject)
+        };
+        format!(
+            "func() {} {{\n{} {{\n{}\n}}{}\n}}()",
+            self.primitive_type_conversion(match_type),
+            switch,
+            cases.join("\n"),
+            no_match,
+        )
+    }
+
+    /**
+     * if/else is an expression too, so it gets the same func wrapping as match,
+     * else if chains are flattened into one Go if statement
+     */
+    fn generate_if(&self, expr: &Expr, if_type: TypeExpr, scope_index: usize) -> String {
+        let if_type = self.resolve_type(if_type, scope_index);
+        let returns_value = if_type != TypeExpr::Void;
+        let mut branches = Vec::new();
+        let mut current = expr;
+
+        while let ExprKind::IfElse(condition, true_branch, false_branch, _) = &current.kind {
+            let true_body = self.generate_body(true_branch, scope_index, returns_value);
+            let branch = match &condition.kind {
+                // narrowed, the subject is the variant's struct in the true branch.
+                // It's shadowed inside the branch, an if's init statement would
+                // shadow it in every else branch as well
+                ExprKind::Is {
+                    subject,
+                    variant,
+                    negated: false,
+                } if matches!(
+                    subject.kind,
+                    ExprKind::ValueReference(MixedIdentifier::Identifier(_), _)
+                ) =>
+                {
+                    let name = self.generate_expr(subject, scope_index);
+                    let variant_type = self.variant_go_type(variant, subject, scope_index);
+                    format!(
+                        "if _, _ok := any({name}).({variant_type}); _ok {{\n{name} := any({name}).({variant_type})\n_ = {name}\n{true_body}\n}}"
+                    )
+                }
+                _ => format!(
+                    "if {} {{\n{}\n}}",
+                    self.generate_expr(condition, scope_index),
+                    true_body
+                ),
+            };
+            branches.push(branch);
+            current = false_branch;
+        }
+        let else_branch = self.generate_body(current, scope_index, returns_value);
+
+        format!(
+            "func() {} {{\n{} else {{\n{}\n}}\n}}()",
+            self.primitive_type_conversion(if_type),
+            branches.join(" else "),
+            else_branch,
+        )
+    }
+
+    /** an interpreted Go string literal, anything Go would choke on is escaped */
+    fn generate_string_literal(&self, string: &str) -> String {
+        let mut literal = String::from("\"");
+        for ch in string.chars() {
+            match ch {
+                '"' => literal.push_str("\\\""),
+                '\\' => literal.push_str("\\\\"),
+                '\n' => literal.push_str("\\n"),
+                '\r' => literal.push_str("\\r"),
+                '\t' => literal.push_str("\\t"),
+                ch if ch.is_control() => literal.push_str(&format!("\\u{:04x}", ch as u32)),
+                ch => literal.push(ch),
+            }
+        }
+        literal.push('"');
+        literal
+    }
+
+    fn primitive_type_conversion(&self, type_expr: TypeExpr) -> String {
         match type_expr {
-            TypeExpr::Number => "float64",
-            TypeExpr::String => "string",
-            TypeExpr::Void => "",
+            TypeExpr::Number => "float64".to_string(),
+            TypeExpr::String => "string".to_string(),
+            TypeExpr::Boolean => "bool".to_string(),
+            TypeExpr::Void => String::new(),
+            TypeExpr::EnumDec(enum_dec) => self.generate_type_name(&enum_dec.identifier),
+            TypeExpr::TypeRef(type_identifier) | TypeExpr::Opaque(type_identifier, _) => {
+                self.generate_type_name(&type_identifier)
+            }
+            TypeExpr::GenericRef(type_identifier, type_args) => {
+                let type_args: Vec<String> = type_args
+                    .into_iter()
+                    .map(|type_arg| self.primitive_type_conversion(type_arg))
+                    .collect();
+                format!(
+                    "{}{}",
+                    self.generate_type_name(&type_identifier),
+                    self.type_args_suffix(&type_identifier, type_args)
+                )
+            }
+            TypeExpr::Scheme { type_expr, .. } => self.primitive_type_conversion(*type_expr),
+            TypeExpr::Array(element_type) => {
+                format!("[]{}", self.primitive_type_conversion(*element_type))
+            }
+            TypeExpr::FunctionDefinition {
+                parameters,
+                return_type,
+                ..
+            } => {
+                let params: Vec<String> = parameters
+                    .into_iter()
+                    .map(|param| self.primitive_type_conversion(param))
+                    .collect();
+                let return_type = self.primitive_type_conversion(*return_type);
+                if return_type.is_empty() {
+                    format!("func({})", params.join(", "))
+                } else {
+                    format!("func({}) {}", params.join(", "), return_type)
+                }
+            }
+            TypeExpr::Record(mut members) => {
+                // Go struct types are only identical when the fields are in the same order
+                members.sort_by(|a, b| a.identifier.name.cmp(&b.identifier.name));
+                let fields: Vec<String> = members
+                    .into_iter()
+                    .map(|member| {
+                        format!(
+                            "{} {}",
+                            self.generate_go_identifier(member.identifier),
+                            self.primitive_type_conversion(member.type_expr)
+                        )
+                    })
+                    .collect();
+                format!("struct {{ {} }}", fields.join("; "))
+            }
+            // the variant's struct, with the args of a generic enum
+            TypeExpr::Variant(enum_type, variant) => format!(
+                "{}{}",
+                self.generate_type_name(&variant),
+                self.go_type_args(*enum_type, 0)
+            ),
+            // solved type vars don't depend on the scope
+            TypeExpr::InferenceRequired(_) => {
+                match self.resolve_type(type_expr.clone(), 0) {
+                    TypeExpr::InferenceRequired(Some(type_var)) => {
+                        match self.type_params.borrow().get(&type_var.name.join(".")) {
+                            Some(type_param) => type_param.clone(),
+                            // nothing constrained this type, e.g. a generic enum that's never constructed
+                            None => "any".to_string(),
+                        }
+                    }
+                    TypeExpr::InferenceRequired(None) => "any".to_string(),
+                    resolved => self.primitive_type_conversion(resolved),
+                }
+            }
             _ => {
-                println!("Codegen: unhandled type_expr to convert {:#?}", type_expr);
+                eprintln!("Codegen: unhandled type_expr to convert {:#?}", type_expr);
                 todo!();
             }
         }
@@ -227,10 +1037,48 @@ impl CodeGenerator {
     }
 
     fn generate_expr(&self, expr: &Expr, scope_index: usize) -> String {
-        match expr {
-            Expr::Number(number) => number.to_string(),
-            Expr::String(string) => format!("\"{}\"", string),
-            Expr::Binary(lhs, op, rhs) => {
+        match &expr.kind {
+            ExprKind::Number(number) => number.to_string(),
+            ExprKind::String(string) => self.generate_string_literal(string),
+            ExprKind::Template(parts) => {
+                let pieces: Vec<String> = parts
+                    .iter()
+                    .map(|part| match part {
+                        TemplatePart::Text(text) => self.generate_string_literal(text),
+                        TemplatePart::Expr(expr) => {
+                            self.use_std_import("fmt");
+                            format!("fmt.Sprint({})", self.generate_expr(expr, scope_index))
+                        }
+                    })
+                    .collect();
+                match pieces.len() {
+                    0 => self.generate_string_literal(""),
+                    _ => pieces.join(" + "),
+                }
+            }
+            ExprKind::Boolean(bool) => bool.to_string(),
+            ExprKind::Record(type_identifier, members, Some(record_type)) => self
+                .generate_record_literal(
+                    type_identifier,
+                    members,
+                    record_type.clone(),
+                    scope_index,
+                ),
+            ExprKind::Match(subject, clauses, Some(match_type)) => {
+                self.generate_match(subject, clauses, match_type.clone(), scope_index)
+            }
+            ExprKind::IfElse(_, _, _, Some(if_type)) => {
+                self.generate_if(expr, if_type.clone(), scope_index)
+            }
+            ExprKind::Binary(lhs, BinaryOp::Power, rhs) => {
+                self.use_std_import("math");
+                format!(
+                    "math.Pow({}, {})",
+                    self.generate_expr(lhs, scope_index),
+                    self.generate_expr(rhs, scope_index)
+                )
+            }
+            ExprKind::Binary(lhs, op, rhs) => {
                 let op_str = match op {
                     BinaryOp::Add => "+",
                     BinaryOp::Subtract => "-",
@@ -242,21 +1090,110 @@ impl CodeGenerator {
                     BinaryOp::GreaterOrEqual => ">=",
                     BinaryOp::LessThan => "<",
                     BinaryOp::LessOrEqual => "<=",
+                    BinaryOp::And => "&&",
+                    BinaryOp::Or => "||",
+                    BinaryOp::Power => unreachable!("** is a call to math.Pow"),
                 };
+                let precedence = go_precedence(op);
                 format!(
                     "{} {} {}",
-                    self.generate_expr(lhs, scope_index),
+                    self.generate_operand(lhs, precedence, false, scope_index),
                     op_str,
-                    self.generate_expr(rhs, scope_index)
+                    self.generate_operand(rhs, precedence, true, scope_index)
+                )
+            }
+            ExprKind::Array(element_type, elements) => {
+                let element_type = self.resolve_type(element_type.clone(), scope_index);
+                let elements: Vec<String> = elements
+                    .iter()
+                    .map(|element| self.generate_expr(element, scope_index))
+                    .collect();
+                format!(
+                    "[]{}{{{}}}",
+                    self.primitive_type_conversion(element_type),
+                    elements.join(", ")
                 )
             }
-            Expr::ValueReference(mixed_identifier) => match mixed_identifier {
+            // numbers are float64, Go wants an int index
+            // Go won't convert a constant like 1.5 to an int, only values
+            // have their fraction dropped, so anything but a name is passed in
+            ExprKind::Index(collection, index, _) => match &index.kind {
+                ExprKind::ValueReference(..) => format!(
+                    "{}[int({})]",
+                    self.generate_expr(collection, scope_index),
+                    self.generate_expr(index, scope_index)
+                ),
+                _ => format!(
+                    "{}[func(i float64) int {{ return int(i) }}({})]",
+                    self.generate_expr(collection, scope_index),
+                    self.generate_expr(index, scope_index)
+                ),
+            },
+            ExprKind::Is {
+                subject,
+                variant,
+                negated,
+            } => format!(
+                "func() bool {{\n_, _ok := any({}).({})\nreturn {}_ok\n}}()",
+                self.generate_expr(subject, scope_index),
+                self.variant_go_type(variant, subject, scope_index),
+                if *negated { "!" } else { "" }
+            ),
+            ExprKind::Unary(op, operand) => {
+                let op_str = match op {
+                    UnaryOp::Negate => "-",
+                    UnaryOp::Not => "!",
+                };
+                // Go's unary operators bind tighter than any binary one, and --x is a statement
+                let operand_go = self.generate_expr(operand, scope_index);
+                match &operand.kind {
+                    ExprKind::Binary(_, op, _) if *op != BinaryOp::Power => {
+                        format!("{}({})", op_str, operand_go)
+                    }
+                    ExprKind::Unary(..) => format!("{}({})", op_str, operand_go),
+                    _ => format!("{}{}", op_str, operand_go),
+                }
+            }
+            ExprKind::ValueReference(mixed_identifier, use_type) => match mixed_identifier {
                 MixedIdentifier::Identifier(identifier) => {
-                    self.generate_go_identifier(identifier.clone())
+                    match self.fn_type_args(&identifier.name, use_type, scope_index) {
+                        Some(type_args) if self.monomorphize => {
+                            let mut instances = self.instances.borrow_mut();
+                            let instance = (identifier.name.clone(), type_args);
+                            if !instances.contains(&instance) {
+                                instances.push(instance.clone());
+                            }
+                            self.instance_name(&instance.0, &instance.1)
+                        }
+                        Some(type_args) => format!(
+                            "{}[{}]",
+                            self.generate_go_identifier(identifier.clone()),
+                            type_args.join(", ")
+                        ),
+                        None => self.generate_go_identifier(identifier.clone()),
+                    }
+                }
+                MixedIdentifier::TypeIdentifier(type_identifier)
+                    if self.is_enum_variant(type_identifier, scope_index) =>
+                {
+                    format!(
+                        "{}{}{{}}",
+                        self.generate_type_name(type_identifier),
+                        use_type
+                            .clone()
+                            .map(|use_type| self.go_type_args(use_type, scope_index))
+                            .unwrap_or_default()
+                    )
+                }
+                // an opaque type's constructor is a Go conversion
+                MixedIdentifier::TypeIdentifier(type_identifier)
+                    if self.opaque_type(type_identifier, scope_index).is_some() =>
+                {
+                    self.generate_type_name(type_identifier)
                 }
                 MixedIdentifier::TypeIdentifier(type_identifier) => {
-                    println!("handled mixediden::typeiden {:#?}", type_identifier);
-                    println!("codegen: {:#?}", self);
+                    eprintln!("handled mixediden::typeiden {:#?}", type_identifier);
+                    eprintln!("codegen: {:#?}", self);
                     // type identifier here is probably a module import reference
                     self.import_map
                         .get(&type_identifier.name[0])
@@ -264,7 +1201,7 @@ impl CodeGenerator {
                         .to_string()
                 }
             },
-            Expr::FunctionCall {
+            ExprKind::FunctionCall {
                 callee,
                 args,
                 generic_args: _,
@@ -273,17 +1210,52 @@ impl CodeGenerator {
                     .iter()
                     .map(|a| self.generate_expr(a, scope_index))
                     .collect();
+                if let ExprKind::ValueReference(
+                    MixedIdentifier::TypeIdentifier(type_identifier),
+                    use_type,
+                ) = &callee.kind
+                {
+                    if self.is_enum_variant(type_identifier, scope_index) {
+                        return format!(
+                            "{}{}{{{}}}",
+                            self.generate_type_name(type_identifier),
+                            use_type
+                                .clone()
+                                .map(|use_type| self.go_type_args(use_type, scope_index))
+                                .unwrap_or_default(),
+                            go_args.join(", ")
+                        );
+                    }
+                }
                 format!(
                     "{}({})",
                     self.generate_expr(callee, scope_index),
                     go_args.join(", ")
                 )
             }
-            Expr::DotCall(expr, identifier) => {
-                let mut lhs = String::new();
-                let mut rhs = String::new();
+            ExprKind::DotCall(expr, identifier) => {
+                // unwrapping converts back to the underlying type
+                if let ExprKind::ValueReference(
+                    MixedIdentifier::TypeIdentifier(type_identifier),
+                    _,
+                ) = &expr.kind
+                {
+                    if let Some(underlying) = self.opaque_type(type_identifier, scope_index) {
+                        if identifier.name == "unwrap" {
+                            let go_type = self.primitive_type_conversion(underlying);
+                            // a func type needs parens to be converted to
+                            if go_type.starts_with("func") {
+                                return format!("({})", go_type);
+                            }
+                            return go_type;
+                        }
+                    }
+                }
+                let lhs = self.generate_expr(expr, scope_index);
+                let mut rhs = self.generate_go_identifier(identifier.clone());
 
-                if let Expr::ValueReference(MixedIdentifier::Identifier(iden)) = *expr.clone() {
+                // extern members can have a different name in Go
+                if let ExprKind::ValueReference(MixedIdentifier::Identifier(iden), _) = &expr.kind {
                     let value_symbol = self
                         .scope_tree
                         .find_value_symbol(scope_index, iden.name.as_str())
@@ -303,28 +1275,498 @@ impl CodeGenerator {
                         match member {
                             ExternMember::Function { external_name, .. }
                             | ExternMember::Variable { external_name, .. } => {
-                                lhs = self.generate_expr(expr, scope_index);
                                 rhs = external_name.to_string();
                             }
                         }
                     }
-                } else {
-                    lhs = self.generate_expr(expr, scope_index);
-                    rhs = self.generate_go_identifier(identifier.clone());
                 }
 
                 format!("{}.{}", lhs, rhs)
             }
+            ExprKind::FunctionDefinition {
+                parameters,
+                return_type,
+                body,
+                scope: Some(fn_scope),
+                ..
+            } => self.generate_fn(
+                "",
+                parameters,
+                return_type.clone(),
+                body,
+                *fn_scope,
+                scope_index,
+            ),
             _ => {
-                println!("Unhandled codegen expr {:#?}", expr);
+                eprintln!("Unhandled codegen expr {:#?}", expr);
                 todo!()
             }
         }
     }
 
+    fn use_std_import(&self, package: &'static str) {
+        let mut std_imports = self.std_imports.borrow_mut();
+        if !std_imports.contains(&package) {
+            std_imports.push(package);
+        }
+    }
+
+    /**
+     * An operand of a binary operator, in parens when Go would otherwise
+     * group it differently than the fyg source did
+     */
+    fn generate_operand(
+        &self,
+        operand: &Expr,
+        parent_precedence: u8,
+        right_side: bool,
+        scope_index: usize,
+    ) -> String {
+        let operand_go = self.generate_expr(operand, scope_index);
+        match &operand.kind {
+            ExprKind::Binary(_, op, _) if *op != BinaryOp::Power => {
+                let precedence = go_precedence(op);
+                if precedence < parent_precedence || (right_side && precedence == parent_precedence)
+                {
+                    format!("({})", operand_go)
+                } else {
+                    operand_go
+                }
+            }
+            _ => operand_go,
+        }
+    }
+
     fn indent(&self, scope_index: usize) -> String {
         let scope_depth = self.scope_tree.scope_depth(scope_index);
         let depth = if scope_depth > 0 { scope_depth - 1 } else { 0 };
         "  ".repeat(depth).to_string()
     }
 }
+
+/** Go's binary operator precedence, ** becomes a call so it never needs parens */
+fn go_precedence(op: &BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Or => 1,
+        BinaryOp::And => 2,
+        BinaryOp::Equal
+        | BinaryOp::NotEqual
+        | BinaryOp::GreaterThan
+        | BinaryOp::GreaterOrEqual
+        | BinaryOp::LessThan
+        | BinaryOp::LessOrEqual => 3,
+        BinaryOp::Add | BinaryOp::Subtract => 4,
+        BinaryOp::Multiply | BinaryOp::Divide => 5,
+        BinaryOp::Power => 6,
+    }
+}
+
+/** pairs the vars of a generic type with what they are in an instance of it */
+fn bind_type_params(
+    generic: &TypeExpr,
+    instance: &TypeExpr,
+    bound: &mut HashMap<String, TypeExpr>,
+) {
+    match (generic, instance) {
+        (TypeExpr::InferenceRequired(Some(type_var)), _) => {
+            bound
+                .entry(type_var.name.join("."))
+                .or_insert_with(|| instance.clone());
+        }
+        (
+            TypeExpr::FunctionDefinition {
+                parameters: generic_params,
+                return_type: generic_return,
+                ..
+            },
+            TypeExpr::FunctionDefinition {
+                parameters: instance_params,
+                return_type: instance_return,
+                ..
+            },
+        ) => {
+            for (generic_param, instance_param) in generic_params.iter().zip(instance_params) {
+                bind_type_params(generic_param, instance_param, bound);
+            }
+            bind_type_params(generic_return, instance_return, bound);
+        }
+        (TypeExpr::Array(generic_element), TypeExpr::Array(instance_element)) => {
+            bind_type_params(generic_element, instance_element, bound)
+        }
+        (TypeExpr::Record(generic_members), TypeExpr::Record(instance_members)) => {
+            for generic_member in generic_members {
+                if let Some(instance_member) = instance_members
+                    .iter()
+                    .find(|member| member.identifier == generic_member.identifier)
+                {
+                    bind_type_params(&generic_member.type_expr, &instance_member.type_expr, bound);
+                }
+            }
+        }
+        (TypeExpr::GenericRef(_, generic_args), TypeExpr::GenericRef(_, instance_args)) => {
+            for (generic_arg, instance_arg) in generic_args.iter().zip(instance_args) {
+                bind_type_params(generic_arg, instance_arg, bound);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use super::*;
+    use crate::{
+        compiler::ModuleMap, constraints::ConstraintCollector, lexer::Lexer, parser::Parser,
+    };
+
+    fn generate(source: &str, monomorphize: bool) -> String {
+        let tokens = Lexer::new(source.to_string()).tokenize();
+        let program = Parser::new(tokens).parse().expect("source should parse");
+        let mut scope_tree = ScopeTree::new(Arc::new(RwLock::new(ModuleMap::new())));
+        let program = scope_tree
+            .bind_program(program)
+            .expect("source should bind");
+        let mut collector = ConstraintCollector::new(&mut scope_tree);
+        let program = collector.collect_program(program);
+        let constraints = collector.constraints;
+        let mut substitution = Substitution::default();
+        substitution
+            .solve(constraints, &scope_tree)
+            .expect("source should type check");
+        CodeGenerator::new(program, scope_tree, substitution)
+            .monomorphize(monomorphize)
+            .generate_go()
+    }
+
+    #[test]
+    fn narrowing_only_shadows_the_subject_in_the_true_branch() {
+        let go = generate(
+            "module Main
+            enum Animal { Dog, Cat, Bird }
+            const name = (a: Animal): String => `animal`
+            const describe = (a: Animal): String => if a is Animal.Dog { `dog` } else if a is Animal.Cat { `cat` } else { name(a) }",
+            false,
+        );
+        assert!(go.contains(
+            "if _, _ok := any(a).(Animal_Dog); _ok {\na := any(a).(Animal_Dog)\n_ = a\n"
+        ));
+        assert!(go.contains(
+            "} else if _, _ok := any(a).(Animal_Cat); _ok {\na := any(a).(Animal_Cat)\n"
+        ));
+        // the else branches still see the enum value
+        assert!(!go.contains("if a, _ok"));
+    }
+
+    #[test]
+    fn strings_are_escaped_for_go() {
+        let go = generate(
+            r#"module Main
+            const s = `say "hi" \\ a\nb\tc\u{1}`"#,
+            false,
+        );
+        assert!(
+            go.contains(r#"var s string = "say \"hi\" \\ a\nb\tc\u0001";"#),
+            "{}",
+            go
+        );
+    }
+
+    #[test]
+    fn arrays_are_slices_and_indexes_are_truncated_when_run() {
+        let go = generate(
+            "module Main
+            const xs = [1, 2, 3]
+            const i = 1
+            const second = xs[i]
+            const alsoSecond = xs[1.5]
+            const grid: Number[][] = [xs]",
+            false,
+        );
+        assert!(
+            go.contains("var xs []float64 = []float64{1, 2, 3};"),
+            "{}",
+            go
+        );
+        assert!(go.contains("var second float64 = xs[int(i)];"), "{}", go);
+        assert!(
+            go.contains("var alsoSecond float64 = xs[func(i float64) int { return int(i) }(1.5)];"),
+            "{}",
+            go
+        );
+        assert!(
+            go.contains("var grid [][]float64 = [][]float64{xs};"),
+            "{}",
+            go
+        );
+    }
+
+    #[test]
+    fn generic_type_decs_are_defined_types() {
+        let go = generate(
+            "module Main
+            type Box<T> = { value: T }
+            const boxed: Box<Number> = { value: 1 }",
+            false,
+        );
+        assert!(go.contains("type Box[T any] struct { value T }"), "{}", go);
+        assert!(
+            go.contains("var boxed struct { value float64 } = struct { value float64 }{value: 1}"),
+            "{}",
+            go
+        );
+    }
+
+    #[test]
+    fn generic_fns_and_enums_are_go_generics() {
+        let go = generate(
+            "module Main
+            enum Option<T> { Some(T), None }
+            const id = (x) => x
+            const one = id(1)
+            const none: Option<Number> = Option.None",
+            false,
+        );
+        assert!(go.contains("func id[T any](x T) T {"), "{}", go);
+        assert!(go.contains("var one float64 = id[float64](1);"), "{}", go);
+        assert!(
+            go.contains("type Option[T any] interface {\n\tisOption()\n}"),
+            "{}",
+            go
+        );
+        assert!(
+            go.contains("type Option_Some[T any] struct {\n\t_0 T\n}"),
+            "{}",
+            go
+        );
+        assert!(go.contains("func (Option_Some[T]) isOption() {}"), "{}", go);
+        assert!(
+            go.contains("var none Option[float64] = Option_None[float64]{};"),
+            "{}",
+            go
+        );
+    }
+
+    #[test]
+    fn monomorphizing_copies_generic_fns_enums_and_type_decs() {
+        let go = generate(
+            "module Main
+            type Box<T> = { value: T }
+            enum Option<T> { Some(T), None }
+            const id = (x) => x
+            const one = id(1)
+            const none: Option<Number> = Option.None
+            const boxed: Box<Number> = { value: 1 }",
+            true,
+        );
+        assert!(go.contains("var one float64 = id_float64(1);"), "{}", go);
+        assert!(
+            go.contains("func id_float64(x float64) float64 {"),
+            "{}",
+            go
+        );
+        assert!(
+            go.contains("var none Option_float64 = Option_None_float64{};"),
+            "{}",
+            go
+        );
+        assert!(
+            go.contains("type Option_float64 interface {\n\tisOption_float64()\n}"),
+            "{}",
+            go
+        );
+        assert!(
+            go.contains("type Option_Some_float64 struct {\n\t_0 float64\n}"),
+            "{}",
+            go
+        );
+        assert!(
+            go.contains("func (Option_None_float64) isOption_float64() {}"),
+            "{}",
+            go
+        );
+        // no Go generics are left
+        assert!(!go.contains(" any]"), "{}", go);
+        assert!(!go.contains("[float64]"), "{}", go);
+    }
+
+    #[test]
+    fn matches_are_switches_returned_from_a_closure() {
+        let go = generate(
+            "module Main
+            const describe = (n: Number): String => match (n) { 1 -> `one`, other -> `many` }",
+            false,
+        );
+        assert!(
+            go.contains("return func() string {\nswitch matchSubject := n; matchSubject {\ncase 1:\n      return \"one\"\ndefault:\nother := matchSubject\n_ = other\n      return \"many\"\n}\n}()"),
+            "{}",
+            go
+        );
+    }
+
+    #[test]
+    fn if_else_is_an_if_returned_from_a_closure() {
+        let go = generate(
+            "module Main
+            const pick = (b: Boolean): Number => if b { 1 } else { 2 }",
+            false,
+        );
+        assert!(
+            go.contains(
+                "return func() float64 {\nif b {\n    return 1\n} else {\n    return 2\n}\n}()"
+            ),
+            "{}",
+            go
+        );
+    }
+
+    #[test]
+    fn enums_are_interfaces_and_variants_are_structs() {
+        let go = generate(
+            "module Main
+            enum Shape { Circle(Number), Square }
+            const c = Shape.Circle(2)
+            const area = (s: Shape): Number => match (s) { Shape.Circle(r) -> r * r, Shape.Square -> 1 }",
+            false,
+        );
+        assert!(
+            go.contains("type Shape interface {\n\tisShape()\n}"),
+            "{}",
+            go
+        );
+        assert!(
+            go.contains("type Shape_Circle struct {\n\t_0 float64\n}"),
+            "{}",
+            go
+        );
+        assert!(go.contains("func (Shape_Circle) isShape() {}"), "{}", go);
+        assert!(go.contains("type Shape_Square struct {}"), "{}", go);
+        assert!(
+            go.contains("var c Shape_Circle = Shape_Circle{2};"),
+            "{}",
+            go
+        );
+        assert!(
+            go.contains("switch matchSubject := any(s).(type) {\ncase Shape_Circle:\n_ = matchSubject\nr := matchSubject._0\n"),
+            "{}",
+            go
+        );
+        assert!(go.contains("panic(\"no match clause matched\")"), "{}", go);
+    }
+
+    #[test]
+    fn records_are_structs() {
+        let go = generate(
+            "module Main
+            type User = { name: String, age: Number }
+            const u: User = { name: `x`, age: 1 }
+            const n = u.name
+            const v = User { name: `y`, age: 2 }",
+            false,
+        );
+        assert!(
+            go.contains("type User = struct { age float64; name string }"),
+            "{}",
+            go
+        );
+        assert!(
+            go.contains("var u struct { age float64; name string } = struct { age float64; name string }{name: \"x\", age: 1};"),
+            "{}",
+            go
+        );
+        assert!(go.contains("var n string = u.name;"), "{}", go);
+        assert!(go.contains("= User{name: \"y\", age: 2};"), "{}", go);
+    }
+
+    #[test]
+    fn interpolations_are_concatenated_with_fmt_sprint() {
+        let go = generate(
+            "module Main
+            const n = 2
+            const s = `n is ${n} and ${n + 1}`",
+            false,
+        );
+        assert!(go.contains("import \"fmt\""), "{}", go);
+        assert!(
+            go.contains(
+                "var s string = \"n is \" + fmt.Sprint(n) + \" and \" + fmt.Sprint(n + 1);"
+            ),
+            "{}",
+            go
+        );
+    }
+
+    #[test]
+    fn operators_keep_their_grouping_and_exponents_use_math_pow() {
+        let go = generate(
+            "module Main
+            const a = 2 ** 3
+            const b = -a
+            const c = !(a > 1 && b <= 2) || a == b
+            const d = a < 1 || a != b",
+            false,
+        );
+        assert!(go.contains("import \"math\""), "{}", go);
+        assert!(go.contains("var a float64 = math.Pow(2, 3);"), "{}", go);
+        assert!(go.contains("var b float64 = -a;"), "{}", go);
+        assert!(
+            go.contains("var c bool = !(a > 1 && b <= 2) || a == b;"),
+            "{}",
+            go
+        );
+        assert!(go.contains("var d bool = a < 1 || a != b;"), "{}", go);
+    }
+
+    #[test]
+    fn function_type_annotations_are_go_func_types() {
+        let go = generate(
+            "module Main
+            type Handler = (Number) => String
+            const apply = (f: (Number) => Number, x: Number): Number => f(x)
+            const twice = apply((n) => n * 2, 3)",
+            false,
+        );
+        assert!(go.contains("type Handler = func(float64) string"), "{}", go);
+        assert!(
+            go.contains("func apply(f func(float64) float64, x float64) float64 {"),
+            "{}",
+            go
+        );
+        assert!(
+            go.contains(
+                "var twice float64 = apply(func(n float64) float64 {\n    return n * 2\n}, 3);"
+            ),
+            "{}",
+            go
+        );
+    }
+
+    #[test]
+    fn booleans_are_go_bools() {
+        let go = generate(
+            "module Main
+            const t: Boolean = true
+            const f = !t
+            const flip = (b: Boolean): Boolean => !b",
+            false,
+        );
+        assert!(go.contains("var t bool = true;"), "{}", go);
+        assert!(go.contains("var f bool = !t;"), "{}", go);
+        assert!(go.contains("func flip(b bool) bool {"), "{}", go);
+    }
+
+    #[test]
+    fn opaque_types_are_defined_go_types() {
+        let go = generate(
+            "module Main
+            opaque type Email = String
+            const e = Email(`a@b`)
+            const raw: String = Email.unwrap(e)",
+            false,
+        );
+        assert!(go.contains("type Email string"), "{}", go);
+        assert!(go.contains("var e Email = Email(\"a@b\");"), "{}", go);
+        assert!(go.contains("var raw string = string(e);"), "{}", go);
+    }
+}
@@ -8,11 +8,12 @@ use std::{
 use glob::glob;
 
 use crate::{
-    analyze::analyze_scope_tree,
-    ast::{MixedIdentifier, Program},
+    analyze::{AnalyzeError, Substitution},
+    ast::{MixedIdentifier, Program, Span},
     codegen::CodeGenerator,
     constraints::ConstraintCollector,
-    lexer::Lexer,
+    diagnostics::{Diagnostic, MessageFormat, SourceFiles},
+    lexer::{Lexer, LexerError},
     parser::{Parser, ParserError},
     scope::ScopeTree,
 };
@@ -24,6 +25,9 @@ pub struct Module {
     pub module_name: String,
     pub exports: Vec<MixedIdentifier>,
     pub program: Option<Program>,
+    pub source_code: String,
+    /** found while loading, reported once the module is processed */
+    pub load_error: Option<CompilerError>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -48,13 +52,18 @@ impl ModuleMap {
 
         self.index_by_name
             .entry(self.modules[index].module_name.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(index);
 
         self.index_by_path
             .insert(self.modules[index].path.clone(), index);
     }
 
+    /** index the next added module will have, used as the file id of its spans */
+    pub fn next_index(&self) -> usize {
+        self.modules.len()
+    }
+
     pub fn get_module_mut(&mut self, index: usize) -> &mut Module {
         &mut self.modules[index]
     }
@@ -72,18 +81,112 @@ impl ModuleMap {
     }
 }
 
-#[derive(Debug, Clone)]
+impl SourceFiles for ModuleMap {
+    fn path(&self, file_id: usize) -> String {
+        self.get_module(file_id).path.display().to_string()
+    }
+
+    fn source(&self, file_id: usize) -> &str {
+        &self.get_module(file_id).source_code
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum CompilerError {
-    ParserError(ParserError),
-    Other { message: String },
+    LexerErrors(Vec<LexerError>),
+    /** every syntax error found in a module */
+    ParserErrors(Vec<ParserError>),
+    /** every type error found in a module */
+    AnalyzeErrors(Vec<AnalyzeError>),
+    UndefinedName {
+        name: String,
+        span: Span,
+    },
+    Redeclaration {
+        name: String,
+        span: Span,
+    },
+    UnknownModule {
+        name: String,
+        span: Span,
+    },
+    UnknownMember {
+        name: String,
+        member: String,
+        span: Span,
+    },
+    /** several independent errors from the same pass */
+    Multiple(Vec<CompilerError>),
+    Other {
+        message: String,
+    },
+}
+
+impl CompilerError {
+    /** Ok when nothing went wrong, otherwise the one error or all of them */
+    pub fn from_errors(mut errors: Vec<CompilerError>) -> Result<(), CompilerError> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(CompilerError::Multiple(errors)),
+        }
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            CompilerError::LexerErrors(errors) => {
+                errors.iter().map(|error| error.diagnostic()).collect()
+            }
+            CompilerError::ParserErrors(errors) => {
+                errors.iter().map(|error| error.diagnostic()).collect()
+            }
+            CompilerError::AnalyzeErrors(errors) => {
+                errors.iter().map(|error| error.diagnostic()).collect()
+            }
+            CompilerError::UndefinedName { name, span } => {
+                vec![
+                    Diagnostic::error("E0201", format!("Cannot find {} in this scope", name))
+                        .with_primary(*span, "not found in this scope"),
+                ]
+            }
+            CompilerError::Redeclaration { name, span } => {
+                vec![
+                    Diagnostic::error("E0202", format!("{} is already declared", name))
+                        .with_primary(*span, "declared again here"),
+                ]
+            }
+            CompilerError::UnknownModule { name, span } => {
+                vec![
+                    Diagnostic::error("E0203", format!("No module named {}", name))
+                        .with_primary(*span, "imported here"),
+                ]
+            }
+            CompilerError::UnknownMember { name, member, span } => {
+                vec![
+                    Diagnostic::error("E0204", format!("{} has no member named {}", name, member))
+                        .with_primary(*span, format!("not part of {}", name)),
+                ]
+            }
+            CompilerError::Multiple(errors) => errors
+                .iter()
+                .flat_map(|error| error.diagnostics())
+                .collect(),
+            CompilerError::Other { message } => vec![Diagnostic::without_code(message.clone())],
+        }
+    }
 }
 
 pub struct CompilerSuccess;
 
 pub struct Compiler {
     module_map: Arc<RwLock<ModuleMap>>,
+    /** from loading the source dirs, reported when compiling starts */
     errors: Vec<CompilerError>,
     scope_tree: ScopeTree,
+    /** solution for every module analysed so far, imports refer to their vars */
+    substitution: Substitution,
+    /** copy generic functions for each set of type args instead of using Go generics */
+    pub monomorphize: bool,
 }
 
 impl Compiler {
@@ -97,11 +200,13 @@ impl Compiler {
             }
         }));
         let scope_tree = ScopeTree::new(Arc::clone(&module_map));
-        println!("Errors: {:#?}", errors);
+        eprintln!("Errors: {:#?}", errors);
         Compiler {
             module_map,
             errors,
             scope_tree,
+            substitution: Substitution::default(),
+            monomorphize: false,
         }
     }
 
@@ -114,7 +219,7 @@ impl Compiler {
 
         for src_dir in source_dirs {
             let pattern = format!("{}/**/*.fyg", src_dir);
-            println!("Loading fyg files from: {}", pattern.clone());
+            eprintln!("Loading fyg files from: {}", pattern.clone());
             let globules = glob(pattern.as_str()).map_err(|_err| CompilerError::Other {
                 message: format!("Error globbing with {}", pattern),
             })?;
@@ -123,8 +228,8 @@ impl Compiler {
                 let path = entry.map_err(|_err| CompilerError::Other {
                     message: "Error get path from globule".to_string(),
                 })?;
-                println!("Found file: {}", path.clone().display());
-                let module = Compiler::build_module_from_filepath(path)?;
+                eprintln!("Found file: {}", path.clone().display());
+                let module = Compiler::build_module_from_filepath(path, module_map.next_index())?;
 
                 module_map.add_module(module);
             }
@@ -133,20 +238,24 @@ impl Compiler {
     }
 
     pub fn compile(&mut self, entry_file_path: PathBuf) -> Result<CompilerSuccess, CompilerError> {
-        println!("starting compiler");
+        eprintln!("starting compiler");
+        CompilerError::from_errors(std::mem::take(&mut self.errors))?;
         if Path::new("./build").exists() {
-            println!("clearing build dir/");
+            eprintln!("clearing build dir/");
             fs::remove_dir_all("./build").expect("Failed to remove build dir");
         }
         fs::create_dir_all("./build").expect("Failed to create build dir");
-        println!("Writing go.mod file");
-        fs::write("./build/go.mod", "module fygbuild").expect("Can write go.mod");
+        eprintln!("Writing go.mod file");
+        // without a go directive Go assumes 1.16, generics need at least 1.18
+        fs::write("./build/go.mod", "module fygbuild\n\ngo 1.18\n").expect("Can write go.mod");
 
         let entry_module_index = {
             let mut module_map = self.module_map.write().expect("can write module_map");
             if module_map.find_module_by_path(&entry_file_path).is_none() {
+                let file_id = module_map.next_index();
                 module_map.add_module(Compiler::build_module_from_filepath(
                     entry_file_path.clone(),
+                    file_id,
                 )?);
             }
             module_map.find_module_by_path(&entry_file_path).unwrap()
@@ -157,6 +266,24 @@ impl Compiler {
         Ok(CompilerSuccess)
     }
 
+    /** every diagnostic for the error, rendered against the modules' source */
+    pub fn render_error(&self, error: &CompilerError, format: MessageFormat) -> String {
+        let module_map = self.module_map.read().expect("can read module_map");
+        let diagnostics = error.diagnostics();
+        match format {
+            MessageFormat::Human => diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.render(&*module_map))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            MessageFormat::Json => diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_json(&*module_map))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }
+    }
+
     pub fn find_modules_by_name(&mut self, name: &str) -> Option<Vec<usize>> {
         let module_map = self
             .module_map
@@ -169,9 +296,12 @@ impl Compiler {
         let program = {
             let mut module_map = self.module_map.write().expect("can write module_map");
             let module = module_map.get_module_mut(module_index);
+            if let Some(load_error) = module.load_error.clone() {
+                return Err(load_error);
+            }
             module.parser.reset();
-            let parsed_program = module.parser.parse().map_err(CompilerError::ParserError)?;
-            println!("parsed program:\n{:#?}", parsed_program);
+            let parsed_program = module.parser.parse().map_err(CompilerError::ParserErrors)?;
+            eprintln!("parsed program:\n{:#?}", parsed_program);
             parsed_program
         };
 
@@ -181,12 +311,13 @@ impl Compiler {
                 Some(imported_module_indices) => {
                     for import_index in imported_module_indices {
                         self.process_module(import_index)?;
-                        println!("found module processed");
+                        eprintln!("found module processed");
                     }
                 }
                 None => {
-                    return Err(CompilerError::Other {
-                        message: format!("No module found named {}", joined_name),
+                    return Err(CompilerError::UnknownModule {
+                        name: joined_name,
+                        span: import.span,
                     });
                 }
             };
@@ -195,11 +326,14 @@ impl Compiler {
         let bound_program = self.scope_tree.bind_program(program)?;
         let mut constraints_collector = ConstraintCollector::new(&mut self.scope_tree);
         let collected_program = constraints_collector.collect_program(bound_program);
-        println!("===program===\n{:#?}\n===", collected_program);
-        let analyze_result =
-            analyze_scope_tree(constraints_collector.constraints, &mut self.scope_tree);
+        eprintln!("===program===\n{:#?}\n===", collected_program);
+        CompilerError::from_errors(constraints_collector.errors)?;
+        let constraints = constraints_collector.constraints;
+        let analyze_result = self.substitution.solve(constraints, &self.scope_tree);
 
-        if analyze_result.is_ok() {
+        if let Err(analyze_errors) = analyze_result {
+            return Err(CompilerError::AnalyzeErrors(analyze_errors));
+        } else {
             let mut module_map = self
                 .module_map
                 .write()
@@ -207,10 +341,14 @@ impl Compiler {
             let module = module_map.get_module_mut(module_index);
             module.program = Some(collected_program.clone());
 
-            let mut code_gen =
-                CodeGenerator::new(collected_program.clone(), self.scope_tree.clone());
+            let mut code_gen = CodeGenerator::new(
+                collected_program.clone(),
+                self.scope_tree.clone(),
+                self.substitution.clone(),
+            )
+            .monomorphize(self.monomorphize);
             let go_code = code_gen.generate_go();
-            println!("Go Program:\n------\n{}\n------", go_code.clone());
+            eprintln!("Go Program:\n------\n{}\n------", go_code.clone());
             let go_filename = format!(
                 "./build/{}.go",
                 module.module_name.to_lowercase().replace('.', "/")
@@ -221,32 +359,43 @@ impl Compiler {
             fs::create_dir_all(go_file_basepath).expect("Create build src dir");
             fs::write(go_filename.clone(), go_code)
                 .unwrap_or_else(|_| panic!("Cannot write to {}", go_filename));
-        } else {
-            panic!("Error: {:#?}", analyze_result.unwrap_err());
         }
 
         Ok(())
     }
 
-    fn build_module_from_filepath(path: PathBuf) -> Result<Module, CompilerError> {
+    fn build_module_from_filepath(path: PathBuf, file_id: usize) -> Result<Module, CompilerError> {
         let source_code =
             fs::read_to_string(path.clone()).map_err(|_err| CompilerError::Other {
                 message: format!("Could not read file {}", path.display()),
             })?;
-        let mut lexer = Lexer::new(source_code);
+        let mut lexer = Lexer::new(source_code.clone()).with_file_id(file_id);
         let tokens = lexer.tokenize();
         let mut parser = Parser::new(tokens);
-        let module_dec = parser
-            .parse_get_module_dec()
-            .map_err(CompilerError::ParserError)?;
 
-        let joined_module_name = module_dec.name.join(".");
+        // the module is kept even when it's broken so its errors can show its source
+        let (module_name, exports, load_error) = match parser.parse_get_module_dec() {
+            _ if !lexer.errors.is_empty() => (
+                String::new(),
+                Vec::new(),
+                Some(CompilerError::LexerErrors(lexer.errors)),
+            ),
+            Ok(module_dec) => (module_dec.name.join("."), module_dec.exports, None),
+            Err(parser_error) => (
+                String::new(),
+                Vec::new(),
+                Some(CompilerError::ParserErrors(vec![parser_error])),
+            ),
+        };
+
         Ok(Module {
             path,
             parser,
-            module_name: joined_module_name,
-            exports: module_dec.exports,
+            module_name,
+            exports,
             program: None,
+            source_code,
+            load_error,
         })
     }
 }
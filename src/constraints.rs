@@ -1,13 +1,26 @@
 use core::fmt;
 use core::panic;
 
-use crate::{ast::*, scope::ScopeTree};
+use crate::{analyze::AnalyzeError, ast::*, compiler::CompilerError, scope::ScopeTree};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ConstraintKind {
     Equality,
+    /** not collected yet */
+    #[allow(dead_code)]
     Subset,
     PatternMatch,
+    /** lhs is a record with the member, rhs is the member's type */
+    Member(Identifier),
+    /**
+     * lhs is a fresh instance of rhs, which might get generalized. Explicit
+     * type args are used for the scheme's vars, in order
+     */
+    Instance(Vec<TypeExpr>),
+    /** lhs is the type of the named const, generalize whatever is still free */
+    Generalize(Identifier),
+    /** lhs has to be something that can be put in a template string */
+    Printable,
 }
 
 impl fmt::Display for ConstraintKind {
@@ -16,6 +29,16 @@ impl fmt::Display for ConstraintKind {
             ConstraintKind::Equality => write!(f, "="),
             ConstraintKind::Subset => write!(f, "><"),
             ConstraintKind::PatternMatch => write!(f, "matches"),
+            ConstraintKind::Member(identifier) => write!(f, "has {}:", identifier.name),
+            ConstraintKind::Instance(type_args) if type_args.is_empty() => {
+                write!(f, "instance of")
+            }
+            ConstraintKind::Instance(type_args) => {
+                let type_args: Vec<String> = type_args.iter().map(|t| t.to_string()).collect();
+                write!(f, "instance with <{}> of", type_args.join(", "))
+            }
+            ConstraintKind::Generalize(identifier) => write!(f, "generalize {}", identifier.name),
+            ConstraintKind::Printable => write!(f, "is printable"),
         }
     }
 }
@@ -26,12 +49,17 @@ pub struct Constraint {
     pub rhs: TypeExpr,
     pub kind: ConstraintKind,
     pub scope_index: usize,
+    pub span: Span,
+    /** where the lhs type was written down, e.g. a const's annotation */
+    pub expected_span: Option<Span>,
 }
 
 #[derive(Debug)]
 pub struct ConstraintCollector<'a> {
     scope_tree: &'a mut ScopeTree,
     pub constraints: Vec<Constraint>,
+    /** names that couldn't be resolved, their uses get the error type */
+    pub errors: Vec<CompilerError>,
 }
 
 impl<'a> ConstraintCollector<'a> {
@@ -39,6 +67,7 @@ impl<'a> ConstraintCollector<'a> {
         ConstraintCollector {
             scope_tree,
             constraints: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
@@ -55,29 +84,22 @@ impl<'a> ConstraintCollector<'a> {
                 TopStatement::ConstDec(const_dec) => {
                     self.collect_const_dec(const_dec.clone(), program.clone().scope.unwrap())
                 }
-                TopStatement::TypeDec(_) => todo!(),
+                // type decs are registered during binding, nothing to infer
+                TopStatement::TypeDec(_) => TypeExpr::Void,
                 TopStatement::Expr(expr) => {
                     self.collect_expr(expr.clone(), program.clone().scope.unwrap())
                 }
-                TopStatement::EnumDec(_) => todo!(),
+                // variants are fully typed during binding, nothing to infer
+                TopStatement::EnumDec(_) => TypeExpr::Void,
                 TopStatement::ExternDec(extern_package) => {
                     self.collect_extern_dec(extern_package.clone(), program.clone().scope.unwrap())
                 }
+                TopStatement::Error(_) => TypeExpr::Error,
             })
             .collect();
         program
     }
 
-    fn collect_statement(&mut self, statement: BlockStatement, parent_scope: usize) -> TypeExpr {
-        match statement {
-            BlockStatement::ConstDec(const_dec) => {
-                self.collect_const_dec(const_dec.clone(), parent_scope)
-            }
-            BlockStatement::Return(expr) => self.collect_expr(expr, parent_scope),
-            BlockStatement::Expr(expr) => self.collect_expr(expr.clone(), parent_scope),
-        }
-    }
-
     fn collect_extern_dec(&mut self, extern_dec: ExternPackage, parent_scope: usize) -> TypeExpr {
         // extern dec already has a fully fleshed out type in the scope tree
         // this is done during scope binding, lets just pull that out
@@ -108,24 +130,222 @@ impl<'a> ConstraintCollector<'a> {
             rhs: expr_type,
             kind: ConstraintKind::Equality,
             scope_index: parent_scope,
+            span: const_dec.value.span,
+            expected_span: const_dec.type_annotation_span,
+        });
+        // everything about the value is known by now (apart from forward references)
+        self.push_constraint(Constraint {
+            lhs: const_type.clone(),
+            rhs: const_type.clone(),
+            kind: ConstraintKind::Generalize(const_dec.identifier),
+            scope_index: parent_scope,
+            span: const_dec.span,
+            expected_span: None,
         });
 
         const_type
     }
 
+    /**
+     * Type of a value where it's used. Generalized values get a fresh instance,
+     * explicit type args pick the instance instead. The use's own type var is
+     * tied to the instance so codegen can find its type args
+     */
+    fn collect_value_reference(
+        &mut self,
+        mixed_identifier: MixedIdentifier,
+        use_type: Option<TypeExpr>,
+        type_args: Vec<TypeExpr>,
+        scope_index: usize,
+        span: Span,
+    ) -> TypeExpr {
+        let instance_type =
+            self.collect_value_instance(mixed_identifier, type_args, scope_index, span);
+        if let Some(use_type) = use_type {
+            self.push_constraint(Constraint {
+                lhs: use_type,
+                rhs: instance_type.clone(),
+                kind: ConstraintKind::Equality,
+                scope_index,
+                span,
+                expected_span: None,
+            });
+        }
+        instance_type
+    }
+
+    fn collect_value_instance(
+        &mut self,
+        mixed_identifier: MixedIdentifier,
+        type_args: Vec<TypeExpr>,
+        scope_index: usize,
+        span: Span,
+    ) -> TypeExpr {
+        let iden_name = match mixed_identifier {
+            MixedIdentifier::Identifier(identifier) => identifier.name,
+            MixedIdentifier::TypeIdentifier(type_identifier) => type_identifier.name.join("."),
+        };
+        eprintln!(
+            "looking up value ref {} in scope {}",
+            iden_name, scope_index
+        );
+        let Some(value_symbol) = self.scope_tree.find_value_symbol(scope_index, &iden_name) else {
+            self.errors.push(CompilerError::UndefinedName {
+                name: iden_name,
+                span,
+            });
+            return TypeExpr::Error;
+        };
+        if !type_args.is_empty() {
+            return self.push_instance(value_symbol.type_expr, type_args, scope_index, span);
+        }
+        match value_symbol.type_expr {
+            scheme @ TypeExpr::Scheme { .. } => self.scope_tree.instantiate(scheme),
+            // might only be generalized once the analyzer gets to its const
+            inferred @ TypeExpr::InferenceRequired(_) => {
+                self.push_instance(inferred, type_args, scope_index, span)
+            }
+            type_expr => type_expr,
+        }
+    }
+
+    /** a fresh var for an instance of type_expr, which gets solved by the analyzer */
+    fn push_instance(
+        &mut self,
+        type_expr: TypeExpr,
+        type_args: Vec<TypeExpr>,
+        scope_index: usize,
+        span: Span,
+    ) -> TypeExpr {
+        let instance_type = self.scope_tree.create_type_var();
+        self.push_constraint(Constraint {
+            lhs: instance_type.clone(),
+            rhs: type_expr,
+            kind: ConstraintKind::Instance(type_args),
+            scope_index,
+            span,
+            expected_span: None,
+        });
+        instance_type
+    }
+
+    /** payload types and the enum type of a variant, with fresh vars for generic enums */
+    fn instantiate_variant(
+        &mut self,
+        variant: &TypeIdentifier,
+        scope_index: usize,
+        span: Span,
+    ) -> Option<(Vec<TypeExpr>, TypeExpr)> {
+        let variant_name = variant.name.join(".");
+        let Some(variant_symbol) = self
+            .scope_tree
+            .find_value_symbol(scope_index, &variant_name)
+        else {
+            self.errors.push(CompilerError::UndefinedName {
+                name: variant_name,
+                span,
+            });
+            return None;
+        };
+
+        let (params, variant_type) = match self.scope_tree.instantiate(variant_symbol.type_expr) {
+            TypeExpr::FunctionDefinition {
+                parameters,
+                return_type,
+                ..
+            } => (parameters, *return_type),
+            variant_type => (Vec::new(), variant_type),
+        };
+        match variant_type {
+            TypeExpr::Variant(enum_type, _) => Some((params, *enum_type)),
+            enum_type => Some((params, enum_type)),
+        }
+    }
+
+    /**
+     * The pattern has the type of the enum, each binding takes the type of the
+     * payload value at the same position
+     */
+    fn collect_variant_pattern(
+        &mut self,
+        variant: &TypeIdentifier,
+        bindings: &[Identifier],
+        clause_scope: usize,
+        span: Span,
+    ) -> TypeExpr {
+        let variant_name = variant.name.join(".");
+        let Some((params, enum_type)) = self.instantiate_variant(variant, clause_scope, span)
+        else {
+            return TypeExpr::Error;
+        };
+        if params.len() != bindings.len() {
+            self.errors
+                .push(CompilerError::AnalyzeErrors(vec![AnalyzeError {
+                    code: "E0110",
+                    message: format!(
+                        "{} has {} payload values but the pattern binds {}",
+                        variant_name,
+                        params.len(),
+                        bindings.len()
+                    ),
+                    lhs: TypeExpr::Variant(Box::new(enum_type.clone()), variant.clone()),
+                    rhs: TypeExpr::Error,
+                    span,
+                    expected_span: None,
+                }]));
+            return enum_type;
+        }
+
+        for (binding, param) in bindings.iter().zip(params) {
+            let binding_type = self
+                .scope_tree
+                .find_value_symbol(clause_scope, &binding.name)
+                .expect("variant binding should be bound in the clause scope")
+                .type_expr;
+            self.push_constraint(Constraint {
+                lhs: binding_type,
+                rhs: param,
+                kind: ConstraintKind::Equality,
+                scope_index: clause_scope,
+                span,
+                expected_span: None,
+            });
+        }
+
+        enum_type
+    }
+
     fn collect_expr(&mut self, expr: Expr, parent_scope: usize) -> TypeExpr {
-        match expr {
-            Expr::Number(_) => TypeExpr::Number,
-            Expr::String(_) => TypeExpr::String,
-            Expr::Boolean(_) => TypeExpr::Boolean,
-            Expr::FunctionDefinition {
+        let span = expr.span;
+        match expr.kind {
+            ExprKind::Number(_) => TypeExpr::Number,
+            ExprKind::String(_) => TypeExpr::String,
+            ExprKind::Template(parts) => {
+                for part in parts {
+                    if let TemplatePart::Expr(expr) = part {
+                        let expr_span = expr.span;
+                        let expr_type = self.collect_expr(expr, parent_scope);
+                        self.push_constraint(Constraint {
+                            lhs: expr_type,
+                            rhs: TypeExpr::Void,
+                            kind: ConstraintKind::Printable,
+                            scope_index: parent_scope,
+                            span: expr_span,
+                            expected_span: None,
+                        });
+                    }
+                }
+                TypeExpr::String
+            }
+            ExprKind::Boolean(_) => TypeExpr::Boolean,
+            ExprKind::FunctionDefinition {
                 parameters: _,
                 return_type: _return_type,
                 body,
                 scope: Some(fn_scope),
                 identifier: Some(identifier),
             } => {
-                println!("fn_name {:#?}", identifier.clone());
+                eprintln!("fn_name {:#?}", identifier.clone());
                 let as_type_iden = TypeIdentifier {
                     name: vec![identifier.clone().name],
                 };
@@ -142,38 +362,28 @@ impl<'a> ConstraintCollector<'a> {
                     ),
                 };
 
+                let body_span = body.span;
                 let body_returns = self.collect_expr(*body, fn_scope);
                 self.push_constraint(Constraint {
                     lhs: return_type,
                     rhs: body_returns,
                     kind: ConstraintKind::Equality,
                     scope_index: fn_scope,
+                    span: body_span,
+                    expected_span: None,
                 });
 
                 // need to ensure identifier is set before here?
                 // bind_const_dec sets it, but what bout anonymous name?
                 fn_type_symbol.type_expr
             }
-            Expr::ValueReference(mixed_identifier) => {
-                let iden_name = match mixed_identifier {
-                    MixedIdentifier::Identifier(identifier) => identifier.clone().name,
-                    MixedIdentifier::TypeIdentifier(type_identifier) => type_identifier
-                        .clone()
-                        .name
-                        .first()
-                        .expect("at least one name")
-                        .to_string(),
-                };
-                println!(
-                    "looking up value ref {} in scope {}",
-                    iden_name, parent_scope
-                );
-                let value_symbol = self.scope_tree.find_value_symbol(parent_scope, &iden_name);
-                if value_symbol.is_none() {
-                    panic!("Could not find {:?} in socpe", iden_name);
-                }
-                value_symbol.unwrap().type_expr
-            }
+            ExprKind::ValueReference(mixed_identifier, use_type) => self.collect_value_reference(
+                mixed_identifier,
+                use_type,
+                Vec::new(),
+                parent_scope,
+                span,
+            ),
             // Expr::TypeDec(type_dec) => {
             //     let type_symbol = self
             //         .scope_tree
@@ -181,26 +391,91 @@ impl<'a> ConstraintCollector<'a> {
             //
             //     type_symbol.unwrap().type_expr
             // }
-            Expr::Record(_, _) => todo!(),
-            Expr::Array(array_type, exprs) => {
+            ExprKind::Record(type_identifier, members, record_type) => {
+                let record_type = record_type
+                    .unwrap_or_else(|| panic!("record at {} should have been bound", span));
+                let literal_type = TypeExpr::Record(
+                    members
+                        .into_iter()
+                        .map(|member| RecordTypeMemeber {
+                            type_expr: self.collect_expr(member.value, parent_scope),
+                            identifier: member.key,
+                            span: member.span,
+                        })
+                        .collect(),
+                );
+                self.push_constraint(Constraint {
+                    lhs: record_type.clone(),
+                    rhs: literal_type,
+                    kind: ConstraintKind::Equality,
+                    scope_index: parent_scope,
+                    span,
+                    expected_span: None,
+                });
+
+                // named literals must also fit the declared type, an instance
+                // of it when it's generic so `Box { value: 1 }` infers the args
+                if let Some(type_identifier) = type_identifier {
+                    self.push_constraint(Constraint {
+                        lhs: record_type.clone(),
+                        rhs: TypeExpr::TypeRef(type_identifier),
+                        kind: ConstraintKind::Instance(Vec::new()),
+                        scope_index: parent_scope,
+                        span,
+                        expected_span: None,
+                    });
+                }
+                record_type
+            }
+            ExprKind::Array(element_type, exprs) => {
                 for expr in exprs {
+                    let expr_span = expr.span;
                     let expr_type = self.collect_expr(expr.clone(), parent_scope);
                     self.push_constraint(Constraint {
-                        lhs: array_type.clone(),
+                        lhs: element_type.clone(),
                         rhs: expr_type,
                         kind: ConstraintKind::Equality,
                         scope_index: parent_scope,
+                        span: expr_span,
+                        expected_span: None,
                     })
                 }
-                array_type
+                TypeExpr::Array(Box::new(element_type))
+            }
+            ExprKind::Index(collection, index, element_type) => {
+                let element_type = element_type
+                    .unwrap_or_else(|| panic!("index at {} should have been bound", span));
+                let (collection_span, index_span) = (collection.span, index.span);
+                let collection_type = self.collect_expr(*collection, parent_scope);
+                let index_type = self.collect_expr(*index, parent_scope);
+                self.push_constraint(Constraint {
+                    lhs: TypeExpr::Array(Box::new(element_type.clone())),
+                    rhs: collection_type,
+                    kind: ConstraintKind::Equality,
+                    scope_index: parent_scope,
+                    span: collection_span,
+                    expected_span: None,
+                });
+                self.push_constraint(Constraint {
+                    lhs: TypeExpr::Number,
+                    rhs: index_type,
+                    kind: ConstraintKind::Equality,
+                    scope_index: parent_scope,
+                    span: index_span,
+                    expected_span: None,
+                });
+                element_type
             }
-            Expr::BlockExpression(statements, scope_index) => {
+            ExprKind::BlockExpression(statements, scope_index) => {
                 let block_scope = scope_index.unwrap();
-                let returned_exprs: Vec<TypeExpr> = statements
+                let statement_count = statements.len();
+                // a block evaluates to its returns and its trailing expression
+                let returned_exprs: Vec<(TypeExpr, Span)> = statements
                     .iter()
-                    .filter_map(|statement| match statement {
+                    .enumerate()
+                    .filter_map(|(index, statement)| match statement {
                         BlockStatement::Return(expr) => {
-                            Some(self.collect_expr(expr.clone(), block_scope))
+                            Some((self.collect_expr(expr.clone(), block_scope), expr.span))
                         }
                         BlockStatement::ConstDec(const_dec) => {
                             // this is yuck? fix mutation in filter_map
@@ -209,63 +484,137 @@ impl<'a> ConstraintCollector<'a> {
                         }
                         BlockStatement::Expr(expr) => {
                             // this is yuck? fix mutation in filter_map
-                            let _ = self.collect_expr(expr.clone(), block_scope);
-                            None
+                            let expr_type = self.collect_expr(expr.clone(), block_scope);
+                            (index + 1 == statement_count).then_some((expr_type, expr.span))
                         }
                     })
                     .collect();
-                let last_return = returned_exprs.last().unwrap_or(&TypeExpr::Void);
-                for returned_expr in returned_exprs.clone() {
-                    if returned_expr != *last_return {
+                let last_return = returned_exprs
+                    .last()
+                    .map(|(type_expr, _)| type_expr.clone())
+                    .unwrap_or(TypeExpr::Void);
+                for (returned_expr, returned_span) in returned_exprs.clone() {
+                    if returned_expr != last_return {
                         self.push_constraint(Constraint {
                             lhs: last_return.clone(),
                             rhs: returned_expr,
                             kind: ConstraintKind::Equality,
                             scope_index: block_scope,
+                            span: returned_span,
+                            expected_span: None,
                         })
                     }
                 }
-                last_return.clone()
+                last_return
             }
-            Expr::Void => TypeExpr::Void,
-            Expr::Binary(left, _op, right) => {
-                let left_type = self.collect_expr(*left, parent_scope);
-                let right_type = self.collect_expr(*right, parent_scope);
+            ExprKind::Void => TypeExpr::Void,
+            ExprKind::Error => TypeExpr::Error,
+            ExprKind::Is {
+                subject, variant, ..
+            } => {
+                let subject_type = self.collect_expr(*subject, parent_scope);
+                if let Some((_, enum_type)) = self.instantiate_variant(&variant, parent_scope, span)
+                {
+                    self.push_constraint(Constraint {
+                        lhs: enum_type,
+                        rhs: subject_type,
+                        kind: ConstraintKind::PatternMatch,
+                        scope_index: parent_scope,
+                        span,
+                        expected_span: None,
+                    });
+                }
+                TypeExpr::Boolean
+            }
+            ExprKind::Unary(op, operand) => {
+                let operand_span = operand.span;
+                let operand_type = self.collect_expr(*operand, parent_scope);
+                let expected_type = match op {
+                    UnaryOp::Negate => TypeExpr::Number,
+                    UnaryOp::Not => TypeExpr::Boolean,
+                };
                 self.push_constraint(Constraint {
-                    lhs: left_type.clone(),
-                    rhs: right_type.clone(),
+                    lhs: expected_type.clone(),
+                    rhs: operand_type,
                     kind: ConstraintKind::Equality,
                     scope_index: parent_scope,
+                    span: operand_span,
+                    expected_span: None,
                 });
-                let push_numbers_constraint = |return_type| {
-                    self.push_constraint(Constraint {
-                        lhs: TypeExpr::Number,
-                        rhs: left_type,
-                        kind: ConstraintKind::Equality,
-                        scope_index: parent_scope,
-                    });
-                    self.push_constraint(Constraint {
-                        lhs: TypeExpr::Number,
+                expected_type
+            }
+            ExprKind::Binary(left, op, right) => {
+                let (left_span, right_span) = (left.span, right.span);
+                let left_type = self.collect_expr(*left, parent_scope);
+                let right_type = self.collect_expr(*right, parent_scope);
+                let (operand_type, return_type) = match op {
+                    BinaryOp::Add
+                    | BinaryOp::Subtract
+                    | BinaryOp::Multiply
+                    | BinaryOp::Divide
+                    | BinaryOp::Power => (Some(TypeExpr::Number), TypeExpr::Number),
+                    BinaryOp::GreaterThan
+                    | BinaryOp::GreaterOrEqual
+                    | BinaryOp::LessThan
+                    | BinaryOp::LessOrEqual => (Some(TypeExpr::Number), TypeExpr::Boolean),
+                    BinaryOp::And | BinaryOp::Or => (Some(TypeExpr::Boolean), TypeExpr::Boolean),
+                    BinaryOp::Equal | BinaryOp::NotEqual => (None, TypeExpr::Boolean),
+                };
+                match operand_type {
+                    // each operand is checked on its own, so a mistake is only reported once
+                    Some(operand_type) => {
+                        self.push_constraint(Constraint {
+                            lhs: operand_type.clone(),
+                            rhs: left_type,
+                            kind: ConstraintKind::Equality,
+                            scope_index: parent_scope,
+                            span: left_span,
+                            expected_span: None,
+                        });
+                        self.push_constraint(Constraint {
+                            lhs: operand_type,
+                            rhs: right_type,
+                            kind: ConstraintKind::Equality,
+                            scope_index: parent_scope,
+                            span: right_span,
+                            expected_span: None,
+                        });
+                    }
+                    None => self.push_constraint(Constraint {
+                        lhs: left_type,
                         rhs: right_type,
                         kind: ConstraintKind::Equality,
                         scope_index: parent_scope,
-                    });
-                    return_type
-                };
-                match _op {
-                    BinaryOp::Add => push_numbers_constraint(TypeExpr::Number),
-                    BinaryOp::Subtract => push_numbers_constraint(TypeExpr::Number),
-                    BinaryOp::Multiply => push_numbers_constraint(TypeExpr::Number),
-                    BinaryOp::Divide => push_numbers_constraint(TypeExpr::Number),
-                    BinaryOp::Equal => TypeExpr::Boolean,
-                    BinaryOp::NotEqual => TypeExpr::Boolean,
-                    BinaryOp::GreaterThan => push_numbers_constraint(TypeExpr::Boolean),
-                    BinaryOp::GreaterOrEqual => push_numbers_constraint(TypeExpr::Boolean),
-                    BinaryOp::LessThan => push_numbers_constraint(TypeExpr::Boolean),
-                    BinaryOp::LessOrEqual => push_numbers_constraint(TypeExpr::Boolean),
+                        span,
+                        expected_span: None,
+                    }),
                 }
+                return_type
             }
-            Expr::DotCall(callee, member_identifier) => {
+            ExprKind::DotCall(callee, member_identifier) => {
+                // members of a type, like `EmailAddress.unwrap`, are value symbols of their own
+                if let ExprKind::ValueReference(
+                    MixedIdentifier::TypeIdentifier(ref type_identifier),
+                    _,
+                ) = callee.kind
+                {
+                    let mut member_type_identifier = type_identifier.clone();
+                    member_type_identifier
+                        .name
+                        .push(member_identifier.name.clone());
+                    if self
+                        .scope_tree
+                        .find_value_symbol(parent_scope, &member_type_identifier.name.join("."))
+                        .is_some()
+                    {
+                        return self.collect_value_instance(
+                            MixedIdentifier::TypeIdentifier(member_type_identifier),
+                            Vec::new(),
+                            parent_scope,
+                            span,
+                        );
+                    }
+                }
                 let callee_type = self.collect_expr(*callee, parent_scope);
                 let resolved_callee_type = self
                     .scope_tree
@@ -289,9 +638,9 @@ impl<'a> ConstraintCollector<'a> {
                             Some(member) => match &member {
                                 ExternMember::Function {
                                     local_name,
-                                    external_name: _,
                                     parameters,
                                     return_type,
+                                    ..
                                 } => {
                                     let type_identifier = TypeIdentifier {
                                         name: vec![package_name, local_name.clone().name],
@@ -307,40 +656,71 @@ impl<'a> ConstraintCollector<'a> {
                                         return_type: Box::new(return_type.clone()),
                                     }
                                 }
-                                ExternMember::Variable {
-                                    local_name,
-                                    external_name,
-                                    value_type,
-                                } => todo!(),
+                                ExternMember::Variable { .. } => {
+                                    todo!("extern variable at {}", span)
+                                }
                             },
                             None => {
-                                panic!("Cannot call {} on {}", member_identifier.name, package_name)
+                                self.errors.push(CompilerError::UnknownMember {
+                                    name: package_name,
+                                    member: member_identifier.name,
+                                    span,
+                                });
+                                TypeExpr::Error
                             }
                         }
                     }
                     TypeExpr::ImportRef(name, module_indexes) => {
-                        println!("name: {}", name);
-                        println!("module_indexes: {:#?}", module_indexes);
+                        eprintln!("name: {}", name);
+                        eprintln!("module_indexes: {:#?}", module_indexes);
                         self.scope_tree
                             .resolve_import_member_type(name.clone(), member_identifier.clone())
                             .unwrap_or_else(|| {
-                                panic!("can resolve {}.{}", name, member_identifier.name)
+                                self.errors.push(CompilerError::UnknownMember {
+                                    name,
+                                    member: member_identifier.name,
+                                    span,
+                                });
+                                TypeExpr::Error
                             })
                     }
+                    // record member access, the callee might not be inferred yet
+                    // so the lookup is left to the analyzer
                     _ => {
-                        panic!(
-                            "Unhandled dotcall type {:#?} /endunhandled",
-                            resolved_callee_type
-                        );
+                        let member_type = self.scope_tree.create_type_var();
+                        self.push_constraint(Constraint {
+                            lhs: callee_type,
+                            rhs: member_type.clone(),
+                            kind: ConstraintKind::Member(member_identifier),
+                            scope_index: parent_scope,
+                            span,
+                            expected_span: None,
+                        });
+                        member_type
                     }
                 }
             }
-            Expr::FunctionCall {
+            ExprKind::FunctionCall {
                 callee,
                 args,
-                generic_args: _,
+                generic_args,
             } => {
-                let callee_type = self.collect_expr(*callee, parent_scope);
+                let callee_type = match callee.kind {
+                    _ if generic_args.is_empty() => self.collect_expr(*callee, parent_scope),
+                    ExprKind::ValueReference(mixed_identifier, use_type) => self
+                        .collect_value_reference(
+                            mixed_identifier,
+                            use_type,
+                            generic_args,
+                            parent_scope,
+                            callee.span,
+                        ),
+                    _ => {
+                        let callee_span = callee.span;
+                        let callee_type = self.collect_expr(*callee, parent_scope);
+                        self.push_instance(callee_type, generic_args, parent_scope, callee_span)
+                    }
+                };
                 let resolved_type = self
                     .scope_tree
                     .resolve_type(callee_type.clone(), parent_scope);
@@ -350,37 +730,39 @@ impl<'a> ConstraintCollector<'a> {
                     TypeExpr::FunctionDefinition { .. } | TypeExpr::FunctionCall { .. }
                 );
 
-                let return_type = self.scope_tree.create_type_var(parent_scope);
-                let fn_call_type = TypeExpr::FunctionCall {
-                    args: args
-                        .iter()
-                        .map(|arg| self.collect_expr(arg.clone(), parent_scope))
-                        .collect(),
-                    return_type: Box::new(return_type.clone()),
-                    callee: Box::new(callee_type.clone()),
-                };
+                let return_type = self.scope_tree.create_type_var();
+                let arg_types: Vec<TypeExpr> = args
+                    .iter()
+                    .map(|arg| self.collect_expr(arg.clone(), parent_scope))
+                    .collect();
 
                 if already_resolves_to_fn {
+                    let fn_call_type = TypeExpr::FunctionCall {
+                        args: arg_types,
+                        return_type: Box::new(return_type.clone()),
+                        callee: Box::new(callee_type.clone()),
+                    };
                     self.push_constraint(Constraint {
                         lhs: fn_call_type,
                         rhs: resolved_type,
                         kind: ConstraintKind::Equality,
                         scope_index: parent_scope,
+                        span,
+                        expected_span: None,
                     });
                 } else {
                     let identifier = match resolved_type.clone() {
                         TypeExpr::TypeRef(type_identifier)  => type_identifier.clone(),
                         TypeExpr::InferenceRequired(Some(type_identifier)) => type_identifier.clone(),
-                        _ => panic!("Expected fn call resolved type to be a TypeIdentifier or InferferenceRequired(Some(TypeIdentifier)). Got {:#?}", resolved_type.clone()),
+                        // the callee was already reported, e.g. an undefined name
+                        TypeExpr::Error => return TypeExpr::Error,
+                        _ => panic!("Expected fn call resolved type to be a TypeIdentifier or InferferenceRequired(Some(TypeIdentifier)) at {}. Got {:#?}", span, resolved_type.clone()),
                     };
                     // existing fn expression is resolving to something like fn1
                     // lets infer a function def based on the call type
                     let fn_def_type = TypeExpr::FunctionDefinition {
                         type_identifier: identifier,
-                        parameters: args
-                            .iter()
-                            .map(|a| self.collect_expr(a.clone(), parent_scope))
-                            .collect(),
+                        parameters: arg_types,
                         return_type: Box::new(return_type.clone()),
                     };
 
@@ -389,13 +771,65 @@ impl<'a> ConstraintCollector<'a> {
                         rhs: fn_def_type,
                         kind: ConstraintKind::Equality,
                         scope_index: parent_scope,
+                        span,
+                        expected_span: None,
                     })
                 }
 
                 return_type
             }
-            Expr::Match(_, _) => todo!(),
-            Expr::IfElse(condition, true_branch, false_branch) => {
+            ExprKind::Match(subject, clauses, match_type) => {
+                let match_type = match_type
+                    .unwrap_or_else(|| panic!("match at {} should have been bound", span));
+                let subject_type = self.collect_expr(*subject, parent_scope);
+
+                for clause in clauses {
+                    let clause_scope = clause.scope.expect("match clause should have a scope");
+                    let pattern_type = match &clause.pattern {
+                        Pattern::String(_) => TypeExpr::String,
+                        Pattern::Number(_) => TypeExpr::Number,
+                        Pattern::Boolean(_) => TypeExpr::Boolean,
+                        Pattern::ValueRef(identifier) => {
+                            self.scope_tree
+                                .find_value_symbol(clause_scope, &identifier.name)
+                                .expect("match binding should be bound in the clause scope")
+                                .type_expr
+                        }
+                        Pattern::Variant(variant, bindings) => self.collect_variant_pattern(
+                            variant,
+                            bindings,
+                            clause_scope,
+                            clause.pattern_span,
+                        ),
+                    };
+                    self.push_constraint(Constraint {
+                        lhs: subject_type.clone(),
+                        rhs: pattern_type,
+                        kind: ConstraintKind::PatternMatch,
+                        scope_index: clause_scope,
+                        span: clause.span,
+                        expected_span: None,
+                    });
+
+                    let body_span = clause.body.span;
+                    let body_type = self.collect_expr(clause.body, clause_scope);
+                    self.push_constraint(Constraint {
+                        lhs: match_type.clone(),
+                        rhs: body_type,
+                        kind: ConstraintKind::Equality,
+                        scope_index: clause_scope,
+                        span: body_span,
+                        expected_span: None,
+                    });
+                }
+
+                match_type
+            }
+            ExprKind::IfElse(condition, true_branch, false_branch, if_type) => {
+                let if_type =
+                    if_type.unwrap_or_else(|| panic!("if at {} should have been bound", span));
+                let (condition_span, true_branch_span, false_branch_span) =
+                    (condition.span, true_branch.span, false_branch.span);
                 let condition_type = self.collect_expr(*condition, parent_scope);
                 let true_branch_type = self.collect_expr(*true_branch, parent_scope);
                 let false_branch_type = self.collect_expr(*false_branch, parent_scope);
@@ -405,16 +839,30 @@ impl<'a> ConstraintCollector<'a> {
                     rhs: TypeExpr::Boolean,
                     kind: ConstraintKind::Equality,
                     scope_index: parent_scope,
+                    span: condition_span,
+                    expected_span: None,
+                });
+                self.push_constraint(Constraint {
+                    lhs: if_type.clone(),
+                    rhs: true_branch_type,
+                    kind: ConstraintKind::Equality,
+                    scope_index: parent_scope,
+                    span: true_branch_span,
+                    expected_span: None,
                 });
                 self.push_constraint(Constraint {
-                    lhs: true_branch_type.clone(),
+                    lhs: if_type.clone(),
                     rhs: false_branch_type,
                     kind: ConstraintKind::Equality,
                     scope_index: parent_scope,
+                    span: false_branch_span,
+                    expected_span: None,
                 });
-                true_branch_type
+                if_type
+            }
+            ExprKind::FunctionDefinition { .. } => {
+                panic!("Fn def has something missing at {}", span)
             }
-            Expr::FunctionDefinition { .. } => panic!("Fn def has something missing"),
         }
     }
 }
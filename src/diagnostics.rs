@@ -0,0 +1,334 @@
+/**
+ * Renders errors the way they're shown to whoever is compiling: the
+ * offending source line with carets under the problem and extra labels
+ * pointing at whatever led to it.
+ *
+ * Codes are stable so they can be searched for:
+ *   E0001 syntax error
+ *   E0002 unexpected character
+ *   E0003 unterminated string
+ *   E0004 malformed number
+ *   E0005 invalid escape sequence
+ *   E0101 types don't match
+ *   E0102 infinite type
+ *   E0103 no such member
+ *   E0104 record members don't match
+ *   E0105 param counts don't match
+ *   E0106 wrong amount of args
+ *   E0107 value can't be interpolated
+ *   E0108 wrong amount of type args
+ *   E0109 opaque type used as its underlying type
+ *   E0110 wrong amount of pattern bindings
+ *   E0201 undefined name
+ *   E0202 redeclaration
+ *   E0203 unknown module
+ *   E0204 unknown member
+ */
+use crate::ast::Span;
+
+/** how diagnostics are written out, picked with --message-format */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageFormat {
+    Human,
+    Json,
+}
+
+/** gives diagnostics the path and source of each file id */
+pub trait SourceFiles {
+    fn path(&self, file_id: usize) -> String;
+    fn source(&self, file_id: usize) -> &str;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    /** primary labels are underlined with ^, secondary ones with - */
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Diagnostic {
+            code: Some(code),
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /** an error nothing in the source can be pointed at for */
+    pub fn without_code(message: impl Into<String>) -> Self {
+        Diagnostic {
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_primary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn render(&self, files: &dyn SourceFiles) -> String {
+        let mut out = match self.code {
+            Some(code) => format!("error[{}]: {}\n", code, self.message),
+            None => format!("error: {}\n", self.message),
+        };
+
+        let snippets: Vec<(usize, String, usize, usize)> = self
+            .labels
+            .iter()
+            .map(|label| {
+                let source = files.source(label.span.file_id);
+                let (line_no, col_no) = line_col(source, label.span.start);
+                let line = source.lines().nth(line_no - 1).unwrap_or("").to_string();
+                let width = label_width(&line, col_no, label.span);
+                (line_no, line, col_no, width)
+            })
+            .collect();
+        let gutter = snippets
+            .iter()
+            .map(|(line_no, ..)| line_no.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let blank = " ".repeat(gutter);
+
+        if let Some(primary) = self.labels.iter().find(|label| label.primary) {
+            let source = files.source(primary.span.file_id);
+            let (line_no, col_no) = line_col(source, primary.span.start);
+            out.push_str(&format!(
+                "{}--> {}:{}:{}\n",
+                blank,
+                files.path(primary.span.file_id),
+                line_no,
+                col_no
+            ));
+        }
+
+        let mut last_location = None;
+        for (label, (line_no, line, col_no, width)) in self.labels.iter().zip(snippets) {
+            // labels on the line just shown share it
+            let location = (label.span.file_id, line_no);
+            if last_location != Some(location) {
+                if last_location.is_some_and(|(file_id, _)| file_id != label.span.file_id) {
+                    out.push_str(&format!(
+                        "{}--> {}:{}:{}\n",
+                        blank,
+                        files.path(label.span.file_id),
+                        line_no,
+                        col_no
+                    ));
+                }
+                out.push_str(&format!("{} |\n", blank));
+                out.push_str(&format!("{:>gutter$} | {}\n", line_no, line));
+                last_location = Some(location);
+            }
+            let marker = if label.primary { "^" } else { "-" };
+            out.push_str(&format!(
+                "{} | {}{} {}\n",
+                blank,
+                " ".repeat(col_no - 1),
+                marker.repeat(width),
+                label.message
+            ));
+        }
+
+        for note in &self.notes {
+            out.push_str(&format!("{} = note: {}\n", blank, note));
+        }
+        out
+    }
+}
+
+impl Diagnostic {
+    /**
+     * One line of JSON for tools, the primary label gives the file and span
+     * and every other label is listed under related
+     */
+    pub fn to_json(&self, files: &dyn SourceFiles) -> String {
+        let primary = self.labels.iter().find(|label| label.primary);
+        let related: Vec<String> = self
+            .labels
+            .iter()
+            .filter(|label| !label.primary)
+            .map(|label| {
+                format!(
+                    "{{\"file\":{},\"span\":{},\"message\":{}}}",
+                    json_string(&files.path(label.span.file_id)),
+                    json_span(label.span, files),
+                    json_string(&label.message)
+                )
+            })
+            .collect();
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+
+        format!(
+            "{{\"file\":{},\"span\":{},\"severity\":\"error\",\"code\":{},\"message\":{},\"label\":{},\"related\":[{}],\"notes\":[{}]}}",
+            primary.map_or("null".to_string(), |label| json_string(
+                &files.path(label.span.file_id)
+            )),
+            primary.map_or("null".to_string(), |label| json_span(label.span, files)),
+            self.code.map_or("null".to_string(), json_string),
+            json_string(&self.message),
+            primary.map_or("null".to_string(), |label| json_string(&label.message)),
+            related.join(","),
+            notes.join(",")
+        )
+    }
+}
+
+fn json_span(span: Span, files: &dyn SourceFiles) -> String {
+    let source = files.source(span.file_id);
+    let (line_no, col_no) = line_col(source, span.start);
+    let (end_line_no, end_col_no) = line_col(source, span.end);
+    format!(
+        "{{\"start\":{},\"end\":{},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}}}",
+        span.start, span.end, line_no, col_no, end_line_no, end_col_no
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for ch in value.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/** 1-based line and column of a byte offset */
+pub fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_no = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let col_no = before[line_start..].chars().count() + 1;
+    (line_no, col_no)
+}
+
+/** how many carets a label needs, spans running past their line stop at its end */
+fn label_width(line: &str, col_no: usize, span: Span) -> usize {
+    let remaining = line.chars().count().saturating_sub(col_no - 1);
+    (span.end.saturating_sub(span.start)).min(remaining).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestFiles(Vec<(&'static str, &'static str)>);
+
+    impl SourceFiles for TestFiles {
+        fn path(&self, file_id: usize) -> String {
+            self.0[file_id].0.to_string()
+        }
+
+        fn source(&self, file_id: usize) -> &str {
+            self.0[file_id].1
+        }
+    }
+
+    #[test]
+    fn renders_snippet_with_primary_and_secondary_labels() {
+        let files = TestFiles(vec![("main.fyg", "module Main\nconst a: String = 1\n")]);
+        let diagnostic = Diagnostic::error("E0101", "Types don't match")
+            .with_primary(Span::new(0, 30, 31), "expected String, found Number")
+            .with_secondary(Span::new(0, 21, 27), "expected because of this annotation");
+
+        assert_eq!(
+            diagnostic.render(&files),
+            "error[E0101]: Types don't match
+ --> main.fyg:2:19
+  |
+2 | const a: String = 1
+  |                   ^ expected String, found Number
+  |          ------ expected because of this annotation
+"
+        );
+    }
+
+    #[test]
+    fn renders_labels_on_different_lines_separately() {
+        let files = TestFiles(vec![(
+            "main.fyg",
+            "module Main\nconst f = (x: Number) => x\nconst y = f(`one`)\n",
+        )]);
+        let diagnostic = Diagnostic::error("E0101", "Types don't match")
+            .with_primary(Span::new(0, 51, 56), "expected Number, found String")
+            .with_secondary(Span::new(0, 26, 32), "declared here")
+            .with_note("arguments are checked against the parameter types");
+
+        assert_eq!(
+            diagnostic.render(&files),
+            "error[E0101]: Types don't match
+ --> main.fyg:3:13
+  |
+3 | const y = f(`one`)
+  |             ^^^^^ expected Number, found String
+  |
+2 | const f = (x: Number) => x
+  |               ------ declared here
+  = note: arguments are checked against the parameter types
+"
+        );
+    }
+
+    #[test]
+    fn json_has_one_line_per_diagnostic_with_related_spans() {
+        let files = TestFiles(vec![("main.fyg", "module Main\nconst a: String = \"1\"\n")]);
+        let diagnostic = Diagnostic::error("E0101", "Types don't match")
+            .with_primary(Span::new(0, 30, 33), "expected String, found \"Number\"")
+            .with_secondary(Span::new(0, 21, 27), "annotation");
+
+        assert_eq!(
+            diagnostic.to_json(&files),
+            concat!(
+                r#"{"file":"main.fyg","span":{"start":30,"end":33,"line":2,"column":19,"end_line":2,"end_column":22},"#,
+                r#""severity":"error","code":"E0101","message":"Types don't match","#,
+                r#""label":"expected String, found \"Number\"","#,
+                r#""related":[{"file":"main.fyg","span":{"start":21,"end":27,"line":2,"column":10,"end_line":2,"end_column":16},"message":"annotation"}],"#,
+                r#""notes":[]}"#
+            )
+        );
+        assert_eq!(
+            Diagnostic::without_code("Could not read file").to_json(&files),
+            r#"{"file":null,"span":null,"severity":"error","code":null,"message":"Could not read file","label":null,"related":[],"notes":[]}"#
+        );
+    }
+}
@@ -1,8 +1,13 @@
+use crate::{ast::Span, diagnostics::Diagnostic};
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum TokenKind {
     // BASICS
-    Number(f64),
+    /** the literal as written, e.g. 1_000.5 */
+    Number(String),
     String(String),
+    /** backtick string with ${} in it */
+    Template(Vec<TemplateToken>),
     Identifier(String),
     TypeIdentifier(String),
     Boolean(bool),
@@ -21,6 +26,8 @@ pub enum TokenKind {
     Minus,
     Divide,
     Asterix,
+    /** ** */
+    DoubleAsterix,
     Dot,
     Comma,
     Colon,
@@ -30,7 +37,15 @@ pub enum TokenKind {
     NotEquality,
     GreaterOrEqual,
     LessOrEqual,
+    /** |> */
     RPipe,
+    /** <| */
+    LPipe,
+    Bang,
+    /** && */
+    LogicalAnd,
+    /** || */
+    LogicalOr,
     NL,
 
     // KEYWORDS
@@ -40,9 +55,13 @@ pub enum TokenKind {
     Import,
     Enum,
     Type,
+    Opaque,
     Exporting,
     Return,
     If,
+    Is,
+    /** !is */
+    NotIs,
     Else,
     Match,
     From,
@@ -61,13 +80,11 @@ pub enum TokenKind {
     // Misc
     Unknown(char),
     Comment(String),
+    /** `///` comment, documents the declaration after it */
+    DocComment(String),
 }
 
 impl TokenKind {
-    pub fn is_number(&self) -> bool {
-        matches!(self, TokenKind::Number(_))
-    }
-
     pub fn is_identifier(&self) -> bool {
         matches!(self, TokenKind::Identifier(_))
     }
@@ -75,14 +92,13 @@ impl TokenKind {
     pub fn is_type_identifier(&self) -> bool {
         matches!(self, TokenKind::TypeIdentifier(_))
     }
+}
 
-    pub fn is_string(&self) -> bool {
-        matches!(self, TokenKind::String(_))
-    }
-
-    pub fn is_boolean(&self) -> bool {
-        matches!(self, TokenKind::Boolean(_))
-    }
+/** a piece of a template string, interpolations are lexed like any other code */
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplateToken {
+    Text(String),
+    Interpolation(Vec<Token>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -90,10 +106,34 @@ pub struct Token {
     pub kind: TokenKind,
     pub line_no: usize,
     pub col_no: usize,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexerError {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+
+impl LexerError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        let label = match self.code {
+            "E0003" => "this string is never closed",
+            "E0004" => "not a valid number",
+            "E0005" => "not a valid escape",
+            _ => "not part of the language",
+        };
+        Diagnostic::error(self.code, self.message.clone()).with_primary(self.span, label)
+    }
 }
 
 pub struct Lexer {
     source_code: String,
+    file_id: usize,
+    /** characters that couldn't be lexed, tokenizing carries on past them */
+    pub errors: Vec<LexerError>,
+    /** byte offset into source_code */
     current_pos: usize,
     line_no: usize,
     col_no: usize,
@@ -103,19 +143,27 @@ impl Lexer {
     pub fn new(source_code: String) -> Self {
         Lexer {
             source_code,
+            file_id: 0,
+            errors: Vec::new(),
             current_pos: 0,
             line_no: 1,
             col_no: 1,
         }
     }
 
+    /** tag every token span with the file the source code came from */
+    pub fn with_file_id(mut self, file_id: usize) -> Self {
+        self.file_id = file_id;
+        self
+    }
+
     fn peek_char(&self) -> Option<char> {
-        self.source_code.chars().nth(self.current_pos)
+        self.source_code[self.current_pos..].chars().next()
     }
 
     fn next_char(&mut self) -> Option<char> {
         let ch = self.peek_char()?;
-        self.current_pos += 1;
+        self.current_pos += ch.len_utf8();
 
         if ch == '\n' {
             self.line_no += 1;
@@ -126,223 +174,547 @@ impl Lexer {
         Some(ch)
     }
 
+    /** the keyword comes next and isn't just the start of a longer identifier */
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        let rest = &self.source_code[self.current_pos..];
+        rest.starts_with(keyword)
+            && !rest[keyword.len()..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /** digits and _ separators, whether the separators are well placed is checked after */
+    fn take_digits(&mut self, number: &mut String) {
+        while let Some(next_ch) = self.peek_char() {
+            if next_ch.is_ascii_digit() || next_ch == '_' {
+                number.push(self.next_char().unwrap());
+            } else {
+                break;
+            }
+        }
+    }
+
     pub fn tokenize(&mut self) -> Vec<Token> {
         let mut tokens = Vec::new();
 
-        while let Some(ch) = self.next_char() {
-            let token = match ch {
-                '(' => TokenKind::LParen,
-                ')' => TokenKind::RParen,
-                '{' => TokenKind::LCurly,
-                '}' => TokenKind::RCurly,
-                '[' => TokenKind::LSquare,
-                ']' => TokenKind::RSquare,
-                '+' => TokenKind::Plus,
-                '*' => TokenKind::Asterix,
-                '-' => TokenKind::Minus,
-                ':' => TokenKind::Colon,
-                ',' => TokenKind::Comma,
-                '\n' => TokenKind::NL,
-                '.' => TokenKind::Dot,
-                '!' => {
-                    if let Some(peek_ch) = self.peek_char() {
-                        match peek_ch {
-                            '=' => {
-                                self.next_char();
-                                TokenKind::NotEquality
-                            }
-                            _ => TokenKind::Unknown('!'),
+        while let Some(token) = self.next_token() {
+            if token.kind != TokenKind::Unknown(' ') {
+                tokens.push(token);
+            }
+        }
+
+        // always end with a NL
+        if !matches!(
+            tokens.last(),
+            Some(Token {
+                kind: TokenKind::NL,
+                ..
+            })
+        ) {
+            let line_no = if let Some(last_token) = tokens.last() {
+                last_token.line_no + 1
+            } else {
+                1
+            };
+            tokens.push(Token {
+                kind: TokenKind::NL,
+                line_no,
+                col_no: 1,
+                span: Span::new(self.file_id, self.current_pos, self.current_pos),
+            });
+        }
+
+        tokens
+    }
+
+    /** whitespace comes back as Unknown(' '), None once the source runs out */
+    fn next_token(&mut self) -> Option<Token> {
+        let token_start = self.current_pos;
+        let ch = self.next_char()?;
+        let token = match ch {
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '{' => TokenKind::LCurly,
+            '}' => TokenKind::RCurly,
+            '[' => TokenKind::LSquare,
+            ']' => TokenKind::RSquare,
+            '+' => TokenKind::Plus,
+            '*' if self.peek_char() == Some('*') => {
+                self.next_char();
+                TokenKind::DoubleAsterix
+            }
+            '*' => TokenKind::Asterix,
+            '-' => {
+                if self.peek_char() == Some('>') {
+                    self.next_char();
+                    TokenKind::SkinnyArrow
+                } else {
+                    TokenKind::Minus
+                }
+            }
+            '|' if self.peek_char() == Some('>') => {
+                self.next_char();
+                TokenKind::RPipe
+            }
+            '|' if self.peek_char() == Some('|') => {
+                self.next_char();
+                TokenKind::LogicalOr
+            }
+            '&' if self.peek_char() == Some('&') => {
+                self.next_char();
+                TokenKind::LogicalAnd
+            }
+            ':' => TokenKind::Colon,
+            ',' => TokenKind::Comma,
+            '\n' => TokenKind::NL,
+            '.' => TokenKind::Dot,
+            '!' => {
+                if let Some(peek_ch) = self.peek_char() {
+                    match peek_ch {
+                        '=' => {
+                            self.next_char();
+                            TokenKind::NotEquality
                         }
-                    } else {
-                        TokenKind::Unknown('!')
+                        'i' if self.peek_keyword("is") => {
+                            self.next_char();
+                            self.next_char();
+                            TokenKind::NotIs
+                        }
+                        _ => TokenKind::Bang,
                     }
+                } else {
+                    TokenKind::Bang
                 }
-                '<' => {
-                    if let Some(peek_ch) = self.peek_char() {
-                        match peek_ch {
-                            '=' => {
-                                self.next_char();
-                                TokenKind::LessOrEqual
-                            }
-                            _ => TokenKind::LAngle,
+            }
+            '<' => {
+                if let Some(peek_ch) = self.peek_char() {
+                    match peek_ch {
+                        '=' => {
+                            self.next_char();
+                            TokenKind::LessOrEqual
                         }
-                    } else {
-                        TokenKind::LAngle
+                        '|' => {
+                            self.next_char();
+                            TokenKind::LPipe
+                        }
+                        _ => TokenKind::LAngle,
                     }
+                } else {
+                    TokenKind::LAngle
                 }
-                '>' => {
-                    if let Some(peek_ch) = self.peek_char() {
-                        match peek_ch {
-                            '=' => {
-                                self.next_char();
-                                TokenKind::GreaterOrEqual
-                            }
-                            _ => TokenKind::RAngle,
+            }
+            '>' => {
+                if let Some(peek_ch) = self.peek_char() {
+                    match peek_ch {
+                        '=' => {
+                            self.next_char();
+                            TokenKind::GreaterOrEqual
                         }
-                    } else {
-                        TokenKind::RAngle
+                        _ => TokenKind::RAngle,
                     }
+                } else {
+                    TokenKind::RAngle
                 }
-                '`' => {
-                    let mut string_content = String::new();
-                    while let Some(next_ch) = self.next_char() {
-                        if next_ch == '`' {
-                            // Look ahead to see if it's a double backtick (escape sequence)
-                            if self.peek_char() == Some('`') {
-                                // Consume the next backtick
-                                self.next_char();
-                                // Append a single backtick to the string content
-                                string_content.push('`');
-                            } else {
-                                // It's a single backtick, end of string
-                                break;
-                            }
-                        } else {
-                            string_content.push(next_ch);
+            }
+            '`' => self.lex_string(token_start),
+            '=' => {
+                if let Some(peek_ch) = self.peek_char() {
+                    match peek_ch {
+                        '>' => {
+                            self.next_char();
+                            TokenKind::FatArrow
+                        }
+                        '=' => {
+                            self.next_char();
+                            TokenKind::Equality
                         }
+                        _ => TokenKind::Assign,
                     }
-                    TokenKind::String(string_content)
+                } else {
+                    TokenKind::Assign
                 }
-                '=' => {
-                    if let Some(peek_ch) = self.peek_char() {
-                        match peek_ch {
-                            '>' => {
-                                self.next_char();
-                                TokenKind::FatArrow
-                            }
-                            '=' => {
-                                self.next_char();
-                                TokenKind::Equality
+            }
+            '/' => {
+                if let Some(peek_ch) = self.peek_char() {
+                    match peek_ch {
+                        // Handle /* */ comments
+                        '*' => {
+                            self.next_char(); // consume '*'
+                            let mut comment = "".to_string();
+                            while let Some(comment_char) = self.next_char() {
+                                // look for */ pattern
+                                if comment_char == '*' && self.peek_char() == Some('/') {
+                                    self.next_char(); // consume closing '/'
+                                    break;
+                                }
+                                comment.push(comment_char)
                             }
-                            _ => TokenKind::Assign,
+                            TokenKind::Comment(comment)
                         }
-                    } else {
-                        TokenKind::Assign
-                    }
-                }
-                '/' => {
-                    if let Some(peek_ch) = self.peek_char() {
-                        match peek_ch {
-                            // Handle /* */ comments
-                            '*' => {
-                                self.next_char(); // consume '*'
-                                let mut comment = "".to_string();
-                                while let Some(comment_char) = self.next_char() {
-                                    // look for */ pattern
-                                    if comment_char == '*' && self.peek_char() == Some('/') {
-                                        self.next_char(); // consume closing '/'
-                                        break;
-                                    }
-                                    comment.push(comment_char)
+                        // Handle // and /// comments, up to the end of the line
+                        '/' => {
+                            self.next_char(); // consume second '/'
+                            let is_doc = self.peek_char() == Some('/')
+                                && self.source_code[self.current_pos..].chars().nth(1) != Some('/');
+                            if is_doc {
+                                self.next_char(); // consume third '/'
+                            }
+                            let mut comment = "".to_string();
+                            while let Some(comment_char) = self.peek_char() {
+                                if comment_char == '\n' {
+                                    break;
                                 }
+                                comment.push(self.next_char().unwrap());
+                            }
+                            if is_doc {
+                                TokenKind::DocComment(comment)
+                            } else {
                                 TokenKind::Comment(comment)
                             }
-                            _ => TokenKind::Divide,
                         }
+                        _ => TokenKind::Divide,
+                    }
+                } else {
+                    TokenKind::Divide
+                }
+            }
+            _ if ch.is_ascii_digit() => {
+                let mut number = ch.to_string();
+                let mut problem = None;
+                self.take_digits(&mut number);
+
+                // fraction, digits are required after the point
+                if self.peek_char() == Some('.') {
+                    number.push(self.next_char().unwrap());
+                    if !self.peek_char().is_some_and(|c| c.is_ascii_digit()) {
+                        problem = Some("expected digits after the decimal point");
+                    }
+                    self.take_digits(&mut number);
+                }
+
+                // exponent, e.g. 1e9 or 2.5E-3
+                if matches!(self.peek_char(), Some('e' | 'E')) {
+                    number.push(self.next_char().unwrap());
+                    if matches!(self.peek_char(), Some('+' | '-')) {
+                        number.push(self.next_char().unwrap());
+                    }
+                    if !self.peek_char().is_some_and(|c| c.is_ascii_digit()) {
+                        problem = problem.or(Some("expected digits in the exponent"));
+                    }
+                    self.take_digits(&mut number);
+                }
+
+                // separators only go between two digits
+                let chars: Vec<char> = number.chars().collect();
+                let misplaced_separator = chars.iter().enumerate().any(|(i, c)| {
+                    *c == '_'
+                        && !(chars[i - 1].is_ascii_digit()
+                            && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit()))
+                });
+                if misplaced_separator {
+                    problem = problem.or(Some("_ can only separate two digits"));
+                }
+
+                if let Some(problem) = problem {
+                    self.errors.push(LexerError {
+                        code: "E0004",
+                        message: format!("Malformed number {}, {}", number, problem),
+                        span: Span::new(self.file_id, token_start, self.current_pos),
+                    });
+                }
+                TokenKind::Number(number)
+            }
+            _ if ch.is_ascii_lowercase() => {
+                let mut identifier = ch.to_string();
+                while let Some(next_ch) = self.peek_char() {
+                    if next_ch.is_ascii_alphanumeric() || next_ch == '_' {
+                        identifier.push(self.next_char().unwrap());
                     } else {
-                        TokenKind::Divide
+                        break;
                     }
                 }
-                _ if ch.is_ascii_digit() => {
-                    let mut number = ch.to_string();
-                    while let Some(next_ch) = self.peek_char() {
-                        if next_ch.is_ascii_digit() {
-                            number.push(self.next_char().unwrap());
-                        } else {
-                            break;
-                        }
+                TokenKind::Identifier(identifier)
+            }
+            _ if ch.is_ascii_uppercase() => {
+                let mut type_identifier = ch.to_string();
+                while let Some(next_ch) = self.peek_char() {
+                    if next_ch.is_ascii_alphanumeric() || next_ch == '_' {
+                        type_identifier.push(self.next_char().unwrap());
+                    } else {
+                        break;
                     }
-                    TokenKind::Number(number.parse().unwrap())
                 }
-                _ if ch.is_ascii_lowercase() => {
-                    let mut identifier = ch.to_string();
-                    while let Some(next_ch) = self.peek_char() {
-                        if next_ch.is_ascii_alphanumeric() || next_ch == '_' {
-                            identifier.push(self.next_char().unwrap());
-                        } else {
-                            break;
-                        }
+                TokenKind::TypeIdentifier(type_identifier)
+            }
+            ' ' | '\t' | '\r' => TokenKind::Unknown(' '),
+            _ => {
+                self.errors.push(LexerError {
+                    code: "E0002",
+                    message: format!("Unexpected character {:?}", ch),
+                    span: Span::new(self.file_id, token_start, self.current_pos),
+                });
+                TokenKind::Unknown(ch)
+            }
+        };
+
+        // NOTE: for better perf move this into a peeking check in identifier lexing
+        let final_token = match token {
+            TokenKind::Identifier(identifier) => match identifier.clone().as_str() {
+                "as" => TokenKind::As,
+                "async" => TokenKind::Async,
+                "await" => TokenKind::Await,
+                "case" => TokenKind::Case,
+                "const" => TokenKind::Const,
+                "else" => TokenKind::Else,
+                "exporting" => TokenKind::Exporting,
+                "extern" => TokenKind::Extern,
+                "enum" => TokenKind::Enum,
+                "false" => TokenKind::Boolean(false),
+                "fn" => TokenKind::Fn,
+                "from" => TokenKind::From,
+                "if" => TokenKind::If,
+                "import" => TokenKind::Import,
+                "is" => TokenKind::Is,
+                "impl" => TokenKind::Impl,
+                "match" => TokenKind::Match,
+                "module" => TokenKind::Module,
+                "offload" => TokenKind::Offload,
+                "opaque" => TokenKind::Opaque,
+                "return" => TokenKind::Return,
+                "switch" => TokenKind::Switch,
+                "true" => TokenKind::Boolean(true),
+                "type" => TokenKind::Type,
+                "when" => TokenKind::When,
+                _ => TokenKind::Identifier(identifier),
+            },
+            _ => token.clone(),
+        };
+
+        Some(Token {
+            kind: final_token,
+            line_no: self.line_no,
+            col_no: self.col_no,
+            span: Span::new(self.file_id, token_start, self.current_pos),
+        })
+    }
+
+    /**
+     * Backtick strings. The escapes are \n \t \\ \` \$ and \u{1F600}, a
+     * doubled backtick is a backtick too.
+     *
+     * A string that starts on a new line is a block: that first newline, a
+     * blank last line and the indentation all its lines share are dropped
+     */
+    fn lex_string(&mut self, token_start: usize) -> TokenKind {
+        let strip_indent = if self.peek_char() == Some('\n') {
+            self.next_char();
+            let raw_end = string_end(&self.source_code, self.current_pos);
+            let indent = block_indent(&self.source_code[self.current_pos..raw_end]);
+            self.skip_indent(indent);
+            Some(indent)
+        } else {
+            None
+        };
+
+        let mut parts = Vec::new();
+        let mut string_content = String::new();
+        // where the last source line starts in string_content, unless it had a ${}
+        let mut line_start = Some(0);
+        let mut terminated = false;
+        while let Some(next_ch) = self.next_char() {
+            match next_ch {
+                '$' if self.peek_char() == Some('{') => {
+                    self.next_char();
+                    parts.push(TemplateToken::Text(std::mem::take(&mut string_content)));
+                    parts.push(TemplateToken::Interpolation(self.interpolation_tokens()));
+                    line_start = None;
+                }
+                // a doubled backtick is a backtick
+                '`' if self.peek_char() == Some('`') => {
+                    self.next_char();
+                    string_content.push('`');
+                }
+                '`' => {
+                    terminated = true;
+                    break;
+                }
+                '\\' => {
+                    if let Some(ch) = self.escape_sequence() {
+                        string_content.push(ch);
                     }
-                    TokenKind::Identifier(identifier)
                 }
-                _ if ch.is_ascii_uppercase() => {
-                    let mut type_identifier = ch.to_string();
-                    while let Some(next_ch) = self.peek_char() {
-                        if next_ch.is_ascii_alphanumeric() || next_ch == '_' {
-                            type_identifier.push(self.next_char().unwrap());
-                        } else {
-                            break;
-                        }
+                '\n' => {
+                    string_content.push('\n');
+                    if let Some(indent) = strip_indent {
+                        self.skip_indent(indent);
                     }
-                    TokenKind::TypeIdentifier(type_identifier)
+                    line_start = Some(string_content.len());
                 }
-                _ => TokenKind::Unknown(ch),
-            };
+                _ => string_content.push(next_ch),
+            }
+        }
 
-            // NOTE: for better perf move this into a peeking check in identifier lexing
-            let final_token = match token {
-                TokenKind::Identifier(identifier) => match identifier.clone().as_str() {
-                    "as" => TokenKind::As,
-                    "async" => TokenKind::Async,
-                    "await" => TokenKind::Await,
-                    "case" => TokenKind::Case,
-                    "const" => TokenKind::Const,
-                    "else" => TokenKind::Else,
-                    "exporting" => TokenKind::Exporting,
-                    "extern" => TokenKind::Extern,
-                    "enum" => TokenKind::Enum,
-                    "false" => TokenKind::Boolean(false),
-                    "fn" => TokenKind::Fn,
-                    "from" => TokenKind::From,
-                    "if" => TokenKind::If,
-                    "import" => TokenKind::Import,
-                    "impl" => TokenKind::Impl,
-                    "match" => TokenKind::Match,
-                    "module" => TokenKind::Module,
-                    "offload" => TokenKind::Offload,
-                    "return" => TokenKind::Return,
-                    "switch" => TokenKind::Switch,
-                    "true" => TokenKind::Boolean(true),
-                    "type" => TokenKind::Type,
-                    "when" => TokenKind::When,
-                    _ => TokenKind::Identifier(identifier),
-                },
-                _ => token.clone(),
-            };
+        if !terminated {
+            self.errors.push(LexerError {
+                code: "E0003",
+                message: "Unterminated string".to_string(),
+                span: Span::new(self.file_id, token_start, self.current_pos),
+            });
+        }
 
-            if final_token != TokenKind::Unknown(' ') {
-                tokens.push(Token {
-                    kind: final_token,
-                    line_no: self.line_no,
-                    col_no: self.col_no,
-                });
+        // the closing backtick sits on its own line
+        if let (Some(_), Some(start)) = (strip_indent, line_start) {
+            if string_content[start..]
+                .chars()
+                .all(|c| c == ' ' || c == '\t')
+            {
+                string_content.truncate(start.saturating_sub(1));
             }
         }
 
-        // always end with a NL
-        if !matches!(
-            tokens.last(),
-            Some(Token {
-                kind: TokenKind::NL,
-                ..
-            })
-        ) {
-            let line_no = if let Some(last_token) = tokens.last() {
-                last_token.line_no + 1
-            } else {
-                1
+        if parts.is_empty() {
+            TokenKind::String(string_content)
+        } else {
+            parts.push(TemplateToken::Text(string_content));
+            TokenKind::Template(parts)
+        }
+    }
+
+    /** the character after a backslash in a string, None if it isn't a known escape */
+    fn escape_sequence(&mut self) -> Option<char> {
+        let escape_start = self.current_pos - 1;
+        let escaped = self.next_char();
+        let ch = match escaped {
+            Some('n') => Some('\n'),
+            Some('t') => Some('\t'),
+            Some('\\') => Some('\\'),
+            Some('`') => Some('`'),
+            Some('$') => Some('$'),
+            Some('u') => self.unicode_escape(),
+            _ => None,
+        };
+        if ch.is_none() {
+            let message = match escaped {
+                Some('u') => "Invalid unicode escape, expected e.g. \\u{1F600}".to_string(),
+                _ => format!(
+                    "Unknown escape sequence {}",
+                    &self.source_code[escape_start..self.current_pos]
+                ),
             };
-            tokens.push(Token {
-                kind: TokenKind::NL,
-                line_no,
-                col_no: 1,
-            })
+            self.errors.push(LexerError {
+                code: "E0005",
+                message,
+                span: Span::new(self.file_id, escape_start, self.current_pos),
+            });
+        }
+        ch
+    }
+
+    /** the {1F600} part of \u{1F600} */
+    fn unicode_escape(&mut self) -> Option<char> {
+        if self.peek_char() != Some('{') {
+            return None;
+        }
+        self.next_char();
+        let mut hex = String::new();
+        while let Some(hex_ch) = self.peek_char() {
+            if !hex_ch.is_ascii_hexdigit() {
+                break;
+            }
+            hex.push(self.next_char().unwrap());
+        }
+        if self.peek_char() != Some('}') || hex.is_empty() || hex.len() > 6 {
+            return None;
+        }
+        self.next_char();
+        char::from_u32(u32::from_str_radix(&hex, 16).ok()?)
+    }
+
+    /** drops up to indent spaces or tabs at the start of a block string line */
+    fn skip_indent(&mut self, indent: usize) {
+        for _ in 0..indent {
+            if !matches!(self.peek_char(), Some(' ' | '\t')) {
+                break;
+            }
+            self.next_char();
         }
+    }
 
+    /**
+     * Lexes the code of a ${} up to its closing curly, curlies opened inside
+     * it have to be closed first. Newlines mean nothing in there
+     */
+    fn interpolation_tokens(&mut self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+        while let Some(token) = self.next_token() {
+            match token.kind {
+                TokenKind::RCurly if depth == 0 => break,
+                TokenKind::LCurly => depth += 1,
+                TokenKind::RCurly => depth -= 1,
+                _ => {}
+            }
+            if !matches!(token.kind, TokenKind::Unknown(' ') | TokenKind::NL) {
+                tokens.push(token);
+            }
+        }
         tokens
     }
 }
 
+/**
+ * Offset of the backtick closing the string whose content starts at start,
+ * skipping over escapes and the code in ${}, which can hold strings itself
+ */
+fn string_end(source: &str, start: usize) -> usize {
+    enum Nesting {
+        String,
+        /** curlies opened inside the ${} */
+        Code(usize),
+    }
+    let mut nesting = vec![Nesting::String];
+    let mut chars = source[start..].char_indices().peekable();
+    while let Some((offset, ch)) = chars.next() {
+        let next = chars.peek().map(|(_, next)| *next);
+        match (nesting.last_mut(), ch) {
+            (Some(Nesting::String), '\\') => {
+                chars.next();
+            }
+            (Some(Nesting::String), '`') if next == Some('`') => {
+                chars.next();
+            }
+            (Some(Nesting::String), '`') => {
+                nesting.pop();
+                if nesting.is_empty() {
+                    return start + offset;
+                }
+            }
+            (Some(Nesting::String), '$') if next == Some('{') => {
+                chars.next();
+                nesting.push(Nesting::Code(0));
+            }
+            (Some(Nesting::Code(_)), '`') => nesting.push(Nesting::String),
+            (Some(Nesting::Code(depth)), '{') => *depth += 1,
+            (Some(Nesting::Code(0)), '}') => {
+                nesting.pop();
+            }
+            (Some(Nesting::Code(depth)), '}') => *depth -= 1,
+            _ => {}
+        }
+    }
+    source.len()
+}
+
+/** indentation shared by the lines of a block string, blank lines don't count */
+fn block_indent(raw: &str) -> usize {
+    raw.split('\n')
+        .filter(|line| !line.chars().all(|c| c == ' ' || c == '\t'))
+        .map(|line| line.chars().take_while(|c| *c == ' ' || *c == '\t').count())
+        .min()
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -359,8 +731,9 @@ mod tests {
             TokenKind::RParen,
             TokenKind::LCurly,
             TokenKind::Return,
-            TokenKind::Number(42.0),
+            TokenKind::Number(String::from("42")),
             TokenKind::RCurly,
+            TokenKind::NL,
         ];
 
         let token_kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind.clone()).collect();
@@ -374,10 +747,11 @@ mod tests {
 
         // Assuming comments are skipped and not returned as tokens
         let expected_tokens = vec![
-            TokenKind::Number(42.0),
+            TokenKind::Number(String::from("42")),
             TokenKind::Comment(String::from(" This is a comment ")),
             TokenKind::Plus,
-            TokenKind::Number(1.0),
+            TokenKind::Number(String::from("1")),
+            TokenKind::NL,
         ];
 
         let token_kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind.clone()).collect();
@@ -394,6 +768,7 @@ mod tests {
             TokenKind::TypeIdentifier(String::from("Foo")),
             TokenKind::Assign,
             TokenKind::TypeIdentifier(String::from("Bar")),
+            TokenKind::NL,
         ];
 
         let token_kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind.clone()).collect();
@@ -427,6 +802,216 @@ mod tests {
             TokenKind::FatArrow,
             TokenKind::Equality,
             TokenKind::Assign,
+            TokenKind::NL,
+        ];
+
+        let token_kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(token_kinds, expected_tokens);
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let mut lexer = Lexer::new(String::from("const hello = `wörld`")).with_file_id(3);
+        let tokens = lexer.tokenize();
+
+        let spans: Vec<(usize, usize)> =
+            tokens.iter().map(|t| (t.span.start, t.span.end)).collect();
+        assert_eq!(spans, vec![(0, 5), (6, 11), (12, 13), (14, 22), (22, 22)]);
+        assert!(tokens.iter().all(|t| t.span.file_id == 3));
+    }
+
+    #[test]
+    fn test_unknown_characters_are_errors() {
+        let mut lexer = Lexer::new(String::from("const a =\t1 # 2\nconst s = `open"));
+        let tokens = lexer.tokenize();
+
+        assert!(!tokens.iter().any(|t| t.kind == TokenKind::Unknown('\t')));
+        let errors: Vec<(&str, usize)> = lexer
+            .errors
+            .iter()
+            .map(|e| (e.code, e.span.start))
+            .collect();
+        assert_eq!(errors, vec![("E0002", 12), ("E0003", 26)]);
+    }
+
+    #[test]
+    fn test_operators() {
+        let mut lexer = Lexer::new(String::from("< > ** * ! != && || |> <| & |"));
+        let token_kinds: Vec<TokenKind> = lexer.tokenize().into_iter().map(|t| t.kind).collect();
+
+        assert_eq!(
+            token_kinds,
+            vec![
+                TokenKind::LAngle,
+                TokenKind::RAngle,
+                TokenKind::DoubleAsterix,
+                TokenKind::Asterix,
+                TokenKind::Bang,
+                TokenKind::NotEquality,
+                TokenKind::LogicalAnd,
+                TokenKind::LogicalOr,
+                TokenKind::RPipe,
+                TokenKind::LPipe,
+                TokenKind::Unknown('&'),
+                TokenKind::Unknown('|'),
+                TokenKind::NL,
+            ]
+        );
+        assert_eq!(lexer.errors.len(), 2);
+    }
+
+    #[test]
+    fn test_number_literals_keep_their_text() {
+        let mut lexer = Lexer::new(String::from("1_000 3.14 1e9 2.5E-3 6e+2"));
+        let numbers: Vec<TokenKind> = lexer
+            .tokenize()
+            .into_iter()
+            .map(|t| t.kind)
+            .filter(|kind| *kind != TokenKind::NL)
+            .collect();
+
+        assert_eq!(
+            numbers,
+            vec!["1_000", "3.14", "1e9", "2.5E-3", "6e+2"]
+                .into_iter()
+                .map(|n| TokenKind::Number(String::from(n)))
+                .collect::<Vec<_>>()
+        );
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_numbers_are_errors() {
+        let mut lexer = Lexer::new(String::from("1__0 2_ 3. 4e 5_.0"));
+        lexer.tokenize();
+
+        let errors: Vec<(&str, usize, usize)> = lexer
+            .errors
+            .iter()
+            .map(|e| (e.code, e.span.start, e.span.end))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("E0004", 0, 4),
+                ("E0004", 5, 7),
+                ("E0004", 8, 10),
+                ("E0004", 11, 13),
+                ("E0004", 14, 18)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_template_strings() {
+        let mut lexer = Lexer::new(String::from("`a ${ {b}.c } d ${`e${f}`}`"));
+        let tokens = lexer.tokenize();
+
+        let TokenKind::Template(parts) = &tokens[0].kind else {
+            panic!("expected a template, got {:?}", tokens[0].kind);
+        };
+        let kinds = |part: &TemplateToken| match part {
+            TemplateToken::Text(text) => vec![TokenKind::String(text.clone())],
+            TemplateToken::Interpolation(tokens) => tokens.iter().map(|t| t.kind.clone()).collect(),
+        };
+        let parts: Vec<Vec<TokenKind>> = parts.iter().map(kinds).collect();
+        assert_eq!(
+            parts,
+            vec![
+                vec![TokenKind::String(String::from("a "))],
+                vec![
+                    TokenKind::LCurly,
+                    TokenKind::Identifier(String::from("b")),
+                    TokenKind::RCurly,
+                    TokenKind::Dot,
+                    TokenKind::Identifier(String::from("c")),
+                ],
+                vec![TokenKind::String(String::from(" d "))],
+                vec![TokenKind::Template(vec![
+                    TemplateToken::Text(String::from("e")),
+                    TemplateToken::Interpolation(vec![Token {
+                        kind: TokenKind::Identifier(String::from("f")),
+                        line_no: 1,
+                        col_no: 24,
+                        span: Span::new(0, 22, 23),
+                    }]),
+                    TemplateToken::Text(String::new()),
+                ])],
+                vec![TokenKind::String(String::new())],
+            ]
+        );
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let mut lexer = Lexer::new(String::from(
+            r#"`say "hi"\n\t\\ \` \${x} \u{1F600} ``` `\q \u{110000}`"#,
+        ));
+        let tokens = lexer.tokenize();
+
+        assert_eq!(
+            tokens[0].kind,
+            TokenKind::String(String::from("say \"hi\"\n\t\\ ` ${x} \u{1F600} `"))
+        );
+        let errors: Vec<(&str, usize, usize)> = lexer
+            .errors
+            .iter()
+            .map(|e| (e.code, e.span.start, e.span.end))
+            .collect();
+        assert_eq!(errors, vec![("E0005", 40, 42), ("E0005", 43, 53)]);
+    }
+
+    #[test]
+    fn test_block_strings_strip_indentation() {
+        let source = "const a = `
+            first
+              indented ${name}
+            last
+            `
+const b = `
+  no closing line`
+const c = `same
+    line`";
+        let mut lexer = Lexer::new(String::from(source));
+        let strings: Vec<TokenKind> = lexer
+            .tokenize()
+            .into_iter()
+            .map(|t| t.kind)
+            .filter(|kind| matches!(kind, TokenKind::String(_) | TokenKind::Template(_)))
+            .collect();
+
+        let TokenKind::Template(parts) = &strings[0] else {
+            panic!("expected a template, got {:?}", strings[0]);
+        };
+        assert_eq!(
+            parts[0],
+            TemplateToken::Text(String::from("first\n  indented "))
+        );
+        assert_eq!(parts[2], TemplateToken::Text(String::from("\nlast")));
+        assert_eq!(
+            strings[1],
+            TokenKind::String(String::from("no closing line"))
+        );
+        assert_eq!(
+            strings[2],
+            TokenKind::String(String::from("same\n    line"))
+        );
+    }
+
+    #[test]
+    fn test_line_and_doc_comments() {
+        let mut lexer = Lexer::new(String::from("/// docs\n1 // note\n//// not docs"));
+        let tokens = lexer.tokenize();
+
+        let expected_tokens = vec![
+            TokenKind::DocComment(String::from(" docs")),
+            TokenKind::NL,
+            TokenKind::Number(String::from("1")),
+            TokenKind::Comment(String::from(" note")),
+            TokenKind::NL,
+            TokenKind::Comment(String::from("// not docs")),
+            TokenKind::NL,
         ];
 
         let token_kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind.clone()).collect();
@@ -7,17 +7,20 @@ mod ast;
 mod codegen;
 mod compiler;
 mod constraints;
+mod diagnostics;
 mod lexer;
 mod parser;
 mod scope;
 
 use crate::{
     compiler::{Compiler, CompilerError},
-    parser::ParserError,
+    diagnostics::MessageFormat,
 };
 
 struct Cli {
     file_path: path::PathBuf,
+    message_format: MessageFormat,
+    monomorphize: bool,
 }
 
 fn main() -> io::Result<()> {
@@ -25,40 +28,67 @@ fn main() -> io::Result<()> {
 
     // Check if we have enough arguments
     if args.len() < 3 {
-        eprintln!("Usage: fyg <file_path>");
+        eprintln!("Usage: fyg build [--message-format=human|json] [--monomorphize] <file_path>");
         std::process::exit(1);
     }
 
-    // Assuming the file path is the last argument
-    let file_path = &args[args.len() - 1];
-    println!("File path provided: {}", file_path);
+    let mut message_format = MessageFormat::Human;
+    let mut monomorphize = false;
+    let mut file_path = None;
+    for arg in args[2..].iter() {
+        match arg.as_str() {
+            "--message-format=human" => message_format = MessageFormat::Human,
+            "--message-format=json" => message_format = MessageFormat::Json,
+            "--monomorphize" => monomorphize = true,
+            flag if flag.starts_with("--") => {
+                eprintln!("Unknown option {}", flag);
+                std::process::exit(1);
+            }
+            _ => file_path = Some(arg),
+        }
+    }
+
+    let Some(file_path) = file_path else {
+        eprintln!("Usage: fyg build [--message-format=human|json] [--monomorphize] <file_path>");
+        std::process::exit(1);
+    };
+    eprintln!("File path provided: {}", file_path);
 
     let args = Cli {
         file_path: path::PathBuf::from(file_path.clone()),
+        message_format,
+        monomorphize,
     };
-    let source_dirs = vec!["./src", "./stdlib"]
+    let source_dirs = ["./src", "./stdlib"]
         .iter()
         .map(|s| s.to_string())
         .collect();
     let mut compiler = Compiler::new(source_dirs);
+    compiler.monomorphize = args.monomorphize;
     let result = compiler.compile(args.file_path);
 
     match result {
         Ok(_success) => Ok(()),
         Err(compiler_error) => {
-            match compiler_error {
-                CompilerError::ParserError(ParserError {
-                    message,
-                    line_no,
-                    col_no,
-                }) => {
-                    println!("Parser error {}:{}: {:#?}", line_no, col_no, message);
+            match args.message_format {
+                MessageFormat::Human => {
+                    eprintln!(
+                        "{}",
+                        compiler.render_error(&compiler_error, MessageFormat::Human)
+                    );
+                    if let CompilerError::AnalyzeErrors(errors) = &compiler_error {
+                        eprintln!("{} type error(s)", errors.len());
+                    }
                 }
-                CompilerError::Other { message } => {
-                    println!("{}", message);
+                // one diagnostic per line on stdout
+                MessageFormat::Json => {
+                    println!(
+                        "{}",
+                        compiler.render_error(&compiler_error, MessageFormat::Json)
+                    );
                 }
             }
-            Ok(())
+            std::process::exit(1);
         }
     }
 }
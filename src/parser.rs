@@ -1,12 +1,15 @@
 use crate::{
     ast::*,
-    lexer::{Token, TokenKind},
+    diagnostics::Diagnostic,
+    lexer::{TemplateToken, Token, TokenKind},
 };
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    /** errors recovered from so far, parsing carried on after each of them */
+    errors: Vec<ParserError>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,50 +17,188 @@ pub struct ParserError {
     pub message: String,
     pub line_no: usize,
     pub col_no: usize,
+    pub span: Span,
 }
 
+/**
+ * How tightly binary operators bind, loosest first:
+ *
+ *   1  <|                right
+ *   2  |>                left
+ *   3  ||                left
+ *   4  &&                left
+ *   5  == !=             left
+ *   6  < > <= >= is !is  left
+ *   7  + -               left
+ *   8  * /               left
+ *   9  **                right
+ *
+ * Unary - and ! sit between * and **, so `-x ** 2` is `-(x ** 2)`
+ */
 fn get_precedence(kind: TokenKind) -> u8 {
     match kind {
-        TokenKind::Plus | TokenKind::Minus => 1,
-        TokenKind::Asterix | TokenKind::Divide => 2,
-        TokenKind::Equality | TokenKind::NotEquality => 3,
-        TokenKind::GreaterOrEqual | TokenKind::LessOrEqual => 4,
+        TokenKind::LPipe => 1,
+        TokenKind::RPipe => 2,
+        TokenKind::LogicalOr => 3,
+        TokenKind::LogicalAnd => 4,
+        TokenKind::Equality | TokenKind::NotEquality => 5,
+        TokenKind::LAngle
+        | TokenKind::RAngle
+        | TokenKind::GreaterOrEqual
+        | TokenKind::LessOrEqual
+        | TokenKind::Is
+        | TokenKind::NotIs => 6,
+        TokenKind::Plus | TokenKind::Minus => 7,
+        TokenKind::Asterix | TokenKind::Divide => 8,
+        TokenKind::DoubleAsterix => POWER_PRECEDENCE,
         _ => 0,
     }
 }
 
+const POWER_PRECEDENCE: u8 = 9;
+
+fn is_right_associative(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::LPipe | TokenKind::DoubleAsterix)
+}
+
+/**
+ * Pipes are sugar for calls. `x |> f(a)` puts x in front of the args, so it's
+ * `f(x, a)`, and `x |> f` is `f(x)`. `f <| x` is just `f(x)`
+ */
+fn desugar_pipe(pipe: TokenKind, lhs: Expr, rhs: Expr, span: Span) -> Expr {
+    let (callee, args, generic_args) = match (pipe, lhs, rhs) {
+        (
+            TokenKind::RPipe,
+            piped,
+            Expr {
+                kind:
+                    ExprKind::FunctionCall {
+                        callee,
+                        mut args,
+                        generic_args,
+                    },
+                ..
+            },
+        ) => {
+            args.insert(0, piped);
+            (callee, args, generic_args)
+        }
+        (TokenKind::RPipe, piped, function) => (Box::new(function), vec![piped], vec![]),
+        (_, function, applied) => (Box::new(function), vec![applied], vec![]),
+    };
+    Expr::new(
+        ExprKind::FunctionCall {
+            callee,
+            args,
+            generic_args,
+        },
+        span,
+    )
+}
+
+fn get_binary_op(kind: TokenKind) -> Option<BinaryOp> {
+    match kind {
+        TokenKind::Plus => Some(BinaryOp::Add),
+        TokenKind::Minus => Some(BinaryOp::Subtract),
+        TokenKind::Asterix => Some(BinaryOp::Multiply),
+        TokenKind::Divide => Some(BinaryOp::Divide),
+        TokenKind::DoubleAsterix => Some(BinaryOp::Power),
+        TokenKind::Equality => Some(BinaryOp::Equal),
+        TokenKind::NotEquality => Some(BinaryOp::NotEqual),
+        TokenKind::RAngle => Some(BinaryOp::GreaterThan),
+        TokenKind::GreaterOrEqual => Some(BinaryOp::GreaterOrEqual),
+        TokenKind::LAngle => Some(BinaryOp::LessThan),
+        TokenKind::LessOrEqual => Some(BinaryOp::LessOrEqual),
+        TokenKind::LogicalAnd => Some(BinaryOp::And),
+        TokenKind::LogicalOr => Some(BinaryOp::Or),
+        _ => None,
+    }
+}
+
+impl ParserError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::error("E0001", self.message.clone())
+            .with_primary(self.span, "not expected here")
+    }
+}
+
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        // plain comments mean nothing to the parser, doc comments are kept for declarations
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| !matches!(token.kind, TokenKind::Comment(_)))
+            .collect();
+        Parser {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+        }
     }
 
     /** seek back to start of token list */
     pub fn reset(&mut self) {
         self.current = 0;
+        self.errors = Vec::new();
     }
 
     fn token_parser_error(&self, msg: &str) -> ParserError {
-        println!("parser error: {}", msg);
-        let token = self.tokens.get(self.current).unwrap();
+        eprintln!("parser error: {}", msg);
+        // past the end, blame the last token (always the trailing NL)
+        let token = self
+            .tokens
+            .get(self.current)
+            .or(self.tokens.last())
+            .expect("lexer always produces a token");
         let got = format!(". Got {:?}", token.kind);
         ParserError {
             message: format!("{}{}", msg, got),
             line_no: token.line_no,
             col_no: token.col_no,
+            span: token.span,
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Program, Vec<ParserError>> {
+        let (program, errors) = self.parse_partial();
+        if errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(errors)
         }
     }
 
-    pub fn parse(&mut self) -> Result<Program, ParserError> {
+    /**
+     * Parses as much as it can, e.g. for a file that's still being typed.
+     * Statements that fail to parse become error nodes and parsing picks up
+     * again on the next line
+     */
+    pub fn parse_partial(&mut self) -> (Program, Vec<ParserError>) {
+        self.errors = Vec::new();
         self.swallow_lines();
-        if let Some(token) = self.next_token() {
-            if token.kind != TokenKind::Module {
-                return Err(self.token_parser_error("Expected module keyword"));
+        let start = self.current_span();
+        let module_dec = match self.peek_token() {
+            Some(token) if token.kind != TokenKind::Module => {
+                Err(self.token_parser_error("Expected module keyword"))
             }
-        }
-        let module_dec = self.parse_get_module_dec()?;
+            _ => self.parse_get_module_dec(),
+        };
+        let module_dec = module_dec.unwrap_or_else(|error| {
+            self.errors.push(error);
+            self.synchronize(TokenKind::NL, None);
+            ModuleDec {
+                name: Vec::new(),
+                exports: Vec::new(),
+                span: self.span_from(start),
+            }
+        });
         self.swallow_lines();
 
-        let imports = self.parse_imports()?;
+        let imports = self.parse_imports().unwrap_or_else(|error| {
+            self.errors.push(error);
+            self.synchronize(TokenKind::NL, None);
+            Vec::new()
+        });
 
         let mut top_level_exprs = Vec::new();
         while self.current < self.tokens.len() {
@@ -65,19 +206,48 @@ impl Parser {
             if self.peek_token().is_none() {
                 break;
             }
-            let top_level_expr = self.parse_top_statement()?;
-            top_level_exprs.push(top_level_expr)
+            let start = self.current_span();
+            match self.parse_top_statement() {
+                Ok(top_level_expr) => top_level_exprs.push(top_level_expr),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize(TokenKind::NL, None);
+                    top_level_exprs.push(TopStatement::Error(self.span_from(start)));
+                }
+            }
         }
 
-        Ok(Program {
+        let program = Program {
             scope: None,
             statements: top_level_exprs,
             module_dec,
             imports,
-        })
+        };
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    /**
+     * Skips the rest of whatever failed to parse, up to the next `until` token
+     * that isn't inside brackets opened along the way, or up to `stop`
+     */
+    fn synchronize(&mut self, until: TokenKind, stop: Option<usize>) {
+        let mut depth = 0;
+        while let Some(token) = self.peek_token() {
+            if Some(self.current) == stop {
+                break;
+            }
+            match token.kind {
+                ref kind if *kind == until && depth <= 0 => break,
+                TokenKind::LCurly | TokenKind::LParen | TokenKind::LSquare => depth += 1,
+                TokenKind::RCurly | TokenKind::RParen | TokenKind::RSquare => depth -= 1,
+                _ => {}
+            }
+            self.next_token();
+        }
     }
 
     pub fn parse_get_module_dec(&mut self) -> Result<ModuleDec, ParserError> {
+        let start = self.current_span();
         let _ = self.consume_expected(TokenKind::Module, "Expected module keyword");
         let module_name = self.parse_module_name()?;
         let mut exports = Vec::new();
@@ -127,6 +297,7 @@ impl Parser {
         Ok(ModuleDec {
             name: module_name,
             exports,
+            span: self.span_from(start),
         })
     }
 
@@ -136,6 +307,7 @@ impl Parser {
         // each iteration will consume up to the next "from" token
         // unless all the import statements have been parsed
         while self.peek_token_kind() == Some(TokenKind::From) {
+            let start = self.current_span();
             let _ = self.consume_expected(TokenKind::From, "from clause")?;
             self.swallow_lines();
 
@@ -143,7 +315,7 @@ impl Parser {
             let package_name = self.parse_module_name()?;
 
             let aliased_name = if let Some(TokenKind::As) = self.peek_token_kind() {
-                self.consume_expected(TokenKind::As, "as keyword");
+                let _ = self.consume_expected(TokenKind::As, "as keyword");
 
                 let aliased_name_token = self.consume_matching_expected(
                     |t| matches!(t.kind, TokenKind::TypeIdentifier(_)),
@@ -161,6 +333,7 @@ impl Parser {
             imports.push(PackageImport {
                 package_name,
                 aliased_name,
+                span: self.span_from(start),
             });
 
             self.require_new_line();
@@ -204,6 +377,7 @@ impl Parser {
 
     fn parse_top_statement(&mut self) -> Result<TopStatement, ParserError> {
         self.swallow_lines();
+        let doc = self.parse_doc_comments();
 
         let peek_token = self
             .peek_token()
@@ -211,6 +385,9 @@ impl Parser {
 
         let top_statement = match peek_token.kind {
             TokenKind::Extern => TopStatement::ExternDec(self.parse_extern()?),
+            TokenKind::Enum => TopStatement::EnumDec(self.parse_enum_dec()?),
+            TokenKind::Type => TopStatement::TypeDec(self.parse_type_dec()?),
+            TokenKind::Opaque => TopStatement::TypeDec(self.parse_opaque_type_dec()?),
             _ => {
                 // assume block-like statement
                 let expr = self.parse_block_statement()?;
@@ -223,18 +400,31 @@ impl Parser {
                 }
             }
         };
+        let top_statement = match top_statement {
+            TopStatement::ConstDec(const_dec) => {
+                TopStatement::ConstDec(ConstDec { doc, ..const_dec })
+            }
+            TopStatement::TypeDec(type_dec) => TopStatement::TypeDec(TypeDec { doc, ..type_dec }),
+            TopStatement::EnumDec(enum_dec) => TopStatement::EnumDec(EnumDec { doc, ..enum_dec }),
+            // nothing to document, the comment is dropped
+            top_statement => top_statement,
+        };
 
-        println!("top statement {:?}", top_statement);
+        eprintln!("top statement {:?}", top_statement);
         Ok(top_statement)
     }
 
     fn parse_block_statement(&mut self) -> Result<BlockStatement, ParserError> {
-        println!("parse_block_statement {:?}", self.peek_token());
+        eprintln!("parse_block_statement {:?}", self.peek_token());
         self.swallow_lines();
+        let doc = self.parse_doc_comments();
         let peek_token = self.peek_token().unwrap();
         let statement = match peek_token.kind {
-            TokenKind::Const => BlockStatement::ConstDec(self.parse_const_dec()?),
-            TokenKind::Return => BlockStatement::Return(self.parse_expr()?),
+            TokenKind::Const => BlockStatement::ConstDec(ConstDec {
+                doc,
+                ..self.parse_const_dec()?
+            }),
+            TokenKind::Return => self.parse_return_statement()?,
             _ => {
                 // assume Expr
                 BlockStatement::Expr(self.parse_expr()?)
@@ -249,13 +439,13 @@ impl Parser {
     }
 
     fn parse_expr_with_precedence(&mut self, min_precedence: u8) -> Result<Expr, ParserError> {
-        println!("parse_expr_with_precedence {:?}", self.peek_token());
+        eprintln!("parse_expr_with_precedence {:?}", self.peek_token());
         let mut lhs = self.parse_primary_expr()?;
-        println!("got lhs primary {:?}", lhs);
+        eprintln!("got lhs primary {:?}", lhs);
 
         loop {
             let should_continue = self.peek_for_expr_continuation();
-            println!("should continue {}", should_continue);
+            eprintln!("should continue {}", should_continue);
 
             if !should_continue {
                 break;
@@ -263,7 +453,18 @@ impl Parser {
 
             self.swallow_lines();
 
-            let peek_precedence = self.peek_token_kind().map(get_precedence).unwrap_or(0);
+            // Leave the token alone if it isn't an operator, e.g. the `{` after a match subject
+            let Some(peek_kind) = self.peek_token_kind() else {
+                break;
+            };
+            let is_pipe = matches!(peek_kind, TokenKind::RPipe | TokenKind::LPipe);
+            let is_variant_test = matches!(peek_kind, TokenKind::Is | TokenKind::NotIs);
+            let binary_op = get_binary_op(peek_kind.clone());
+            if binary_op.is_none() && !is_pipe && !is_variant_test {
+                eprintln!("not a binary op, break {:?}", self.peek_token());
+                break;
+            }
+            let peek_precedence = get_precedence(peek_kind.clone());
 
             // If the next token's precedence is less than the minimum, exit the loop
             if peek_precedence < min_precedence {
@@ -271,64 +472,95 @@ impl Parser {
             }
 
             // Consume the operator because its precedence is high enough
-            if let Some(op_token) = self.next_token() {
-                let binary_op = match op_token.kind {
-                    TokenKind::Plus => BinaryOp::Add,
-                    TokenKind::Minus => BinaryOp::Subtract,
-                    TokenKind::Asterix => BinaryOp::Multiply,
-                    TokenKind::Divide => BinaryOp::Divide,
-                    TokenKind::Equality => BinaryOp::Equal,
-                    TokenKind::GreaterOrEqual => BinaryOp::GreaterOrEqual,
-                    TokenKind::LessOrEqual => BinaryOp::LessOrEqual,
-                    _ => {
-                        println!("not a binary op, break {:#?}", op_token);
-                        break;
-                    }
-                };
-
-                // Parse the right-hand side of the operator at a higher precedence
-                let rhs = self.parse_expr_with_precedence(peek_precedence + 1)?;
+            self.next_token();
+
+            // the right of is is a variant name, not an expression
+            if is_variant_test {
+                let variant = self.parse_type_identifier()?;
+                let span = self.span_from(lhs.span);
+                lhs = Expr::new(
+                    ExprKind::Is {
+                        subject: Box::new(lhs),
+                        variant,
+                        negated: peek_kind == TokenKind::NotIs,
+                    },
+                    span,
+                );
+                continue;
+            }
 
-                // Combine lhs and rhs with the operator into a new lhs
-                lhs = Expr::Binary(Box::new(lhs), binary_op, Box::new(rhs));
+            // Parse the right-hand side of the operator at a higher precedence,
+            // unless the same operator can follow on the right
+            let rhs_precedence = if is_right_associative(&peek_kind) {
+                peek_precedence
             } else {
-                break;
-            }
+                peek_precedence + 1
+            };
+            let rhs = self.parse_expr_with_precedence(rhs_precedence)?;
+
+            // Combine lhs and rhs with the operator into a new lhs
+            let span = lhs.span.to(rhs.span);
+            lhs = match binary_op {
+                Some(binary_op) => Expr::new(
+                    ExprKind::Binary(Box::new(lhs), binary_op, Box::new(rhs)),
+                    span,
+                ),
+                None => desugar_pipe(peek_kind, lhs, rhs, span),
+            };
         }
 
         Ok(lhs)
     }
 
     fn parse_primary_expr(&mut self) -> Result<Expr, ParserError> {
-        println!("parse primary expr: {:?}", self.peek_token());
-        let peek_token = self.peek_token().unwrap();
+        eprintln!("parse primary expr: {:?}", self.peek_token());
+        let Some(peek_token) = self.peek_token() else {
+            return Err(self.token_parser_error("Expected an expression"));
+        };
         let expr = match peek_token.kind {
             TokenKind::Number(_) => self.parse_number_expr()?,
             TokenKind::String(_) => self.parse_string_expr()?,
+            TokenKind::Template(_) => self.parse_template_expr()?,
+            TokenKind::Minus | TokenKind::Bang => self.parse_unary_expr()?,
+            TokenKind::LSquare => self.parse_array_literal()?,
             TokenKind::Boolean(_) => self.parse_boolean_expr()?,
             TokenKind::Identifier(_) | TokenKind::TypeIdentifier(_) => self.parse_iden_or_call()?,
             TokenKind::LParen => {
                 if self.peek_for_fn_defition()? {
-                    println!("detected fn def {:?}", self.peek_token());
+                    eprintln!("detected fn def {:?}", self.peek_token());
                     return self.parse_fn_definition();
                 }
-                println!("have lparen but not fn def");
+                eprintln!("have lparen but not fn def");
 
                 let _ = self.consume_expected(TokenKind::LParen, "opening parenthesis");
                 let expr = self.parse_expr()?;
                 let _ = self.consume_expected(TokenKind::RParen, "closing parenthesis");
                 expr
             }
-            TokenKind::LCurly => Expr::BlockExpression(self.parse_block_expr()?, None),
+            TokenKind::Match => self.parse_match_expr()?,
+            TokenKind::If => self.parse_if_expr()?,
+            TokenKind::LCurly if self.peek_for_record_literal() => {
+                self.parse_record_literal(None, self.current_span())?
+            }
+            TokenKind::LCurly => self.parse_block()?,
             _ => {
-                println!("Unhandled token {:?}", peek_token);
-                todo!()
+                eprintln!("Unhandled token {:?}", peek_token);
+                return Err(self.token_parser_error("Expected an expression"));
             }
         };
 
         Ok(expr)
     }
 
+    fn parse_block(&mut self) -> Result<Expr, ParserError> {
+        let start = self.current_span();
+        let statements = self.parse_block_expr()?;
+        Ok(Expr::new(
+            ExprKind::BlockExpression(statements, None),
+            self.span_from(start),
+        ))
+    }
+
     fn parse_block_expr(&mut self) -> Result<Vec<BlockStatement>, ParserError> {
         let closing_curly_pos =
             self.find_matching_closing_paren(TokenKind::LCurly, TokenKind::RCurly)?;
@@ -337,7 +569,18 @@ impl Parser {
         let mut statements = Vec::new();
         while self.current < closing_curly_pos {
             self.swallow_lines();
-            statements.push(self.parse_block_statement()?);
+            let start = self.current_span();
+            match self.parse_block_statement() {
+                Ok(statement) => statements.push(statement),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize(TokenKind::NL, Some(closing_curly_pos));
+                    statements.push(BlockStatement::Expr(Expr::new(
+                        ExprKind::Error,
+                        self.span_from(start),
+                    )));
+                }
+            }
             self.swallow_lines();
         }
 
@@ -346,7 +589,293 @@ impl Parser {
         Ok(statements)
     }
 
+    fn parse_if_expr(&mut self) -> Result<Expr, ParserError> {
+        let start = self.current_span();
+        let _ = self.consume_expected(TokenKind::If, "if keyword")?;
+        let condition = self.parse_expr()?;
+        self.swallow_lines();
+        let true_branch = self.parse_block()?;
+
+        // else is mandatory, but it can start on the line after the closing curly
+        if self.peek_kind_past_lines() == Some(TokenKind::Else) {
+            self.swallow_lines();
+        }
+        let _ = self.consume_expected(TokenKind::Else, "else branch, if expressions need one")?;
+        self.swallow_lines();
+        let false_branch = if self.peek_expected_kind(TokenKind::If) {
+            self.parse_if_expr()?
+        } else {
+            self.parse_block()?
+        };
+
+        Ok(Expr::new(
+            ExprKind::IfElse(
+                Box::new(condition),
+                Box::new(true_branch),
+                Box::new(false_branch),
+                None,
+            ),
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_match_expr(&mut self) -> Result<Expr, ParserError> {
+        let start = self.current_span();
+        let _ = self.consume_expected(TokenKind::Match, "match keyword")?;
+        let subject = self.parse_expr()?;
+        self.swallow_lines();
+
+        let closing_curly_pos =
+            self.find_matching_closing_paren(TokenKind::LCurly, TokenKind::RCurly)?;
+        let _ = self.consume_expected(TokenKind::LCurly, "opening curly after match subject")?;
+
+        let mut clauses = Vec::new();
+        self.swallow_lines();
+        while self.current < closing_curly_pos {
+            let clause_start = self.current_span();
+            let pattern = self.parse_pattern()?;
+            let pattern_span = self.span_from(clause_start);
+            let _ = self.consume_expected(TokenKind::SkinnyArrow, "-> after match pattern")?;
+            self.swallow_lines();
+            let body = self.parse_expr()?;
+            clauses.push(MatchClause {
+                pattern,
+                pattern_span,
+                body,
+                scope: None,
+                span: self.span_from(clause_start),
+            });
+
+            // clauses are separated by newlines, a trailing comma is also fine
+            if self.current < closing_curly_pos
+                && self.consume_if(|t| t.kind == TokenKind::Comma).is_none()
+            {
+                if let Some(error) = self.require_new_line() {
+                    return Err(error);
+                }
+            }
+            self.swallow_lines();
+        }
+        let _ = self.consume_expected(TokenKind::RCurly, "closing curly of match")?;
+
+        if clauses.is_empty() {
+            return Err(self.token_parser_error("match needs at least one clause"));
+        }
+
+        Ok(Expr::new(
+            ExprKind::Match(Box::new(subject), clauses, None),
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, ParserError> {
+        if self
+            .peek_token_kind()
+            .is_some_and(|k| k.is_type_identifier())
+        {
+            return self.parse_variant_pattern();
+        }
+        let pattern = match self.peek_token_kind() {
+            Some(TokenKind::String(string)) => Pattern::String(string),
+            Some(TokenKind::Number(number)) => Pattern::Number(number.to_string()),
+            Some(TokenKind::Boolean(bool)) => Pattern::Boolean(bool),
+            Some(TokenKind::Identifier(name)) => Pattern::ValueRef(Identifier { name }),
+            _ => return Err(self.token_parser_error("Expected a match pattern")),
+        };
+        self.next_token();
+        Ok(pattern)
+    }
+
+    /** e.g. `Animal.Dog(dog)`, payload values are bound to the identifiers */
+    fn parse_variant_pattern(&mut self) -> Result<Pattern, ParserError> {
+        let variant = self.parse_type_identifier()?;
+        let mut bindings = Vec::new();
+        if self.consume_if(|t| t.kind == TokenKind::LParen).is_some() {
+            while !self.peek_expected_kind(TokenKind::RParen) {
+                bindings.push(self.parse_identifier()?);
+                if self.consume_if(|t| t.kind == TokenKind::Comma).is_none() {
+                    break;
+                }
+            }
+            let _ = self.consume_expected(TokenKind::RParen, "closing paren of variant pattern")?;
+        }
+        Ok(Pattern::Variant(variant, bindings))
+    }
+
+    fn parse_type_dec(&mut self) -> Result<TypeDec, ParserError> {
+        let start = self.current_span();
+        let _ = self.consume_expected(TokenKind::Type, "type keyword")?;
+        let identifier = self.parse_type_identifier()?;
+        let type_vars = self.parse_type_vars()?;
+        let _ = self.consume_expected(TokenKind::Assign, "= after type name")?;
+        self.swallow_lines();
+        let type_val = self.parse_type_expr()?;
+
+        Ok(TypeDec {
+            doc: None,
+            identifier,
+            type_vars,
+            type_val,
+            opaque: false,
+            scope: None,
+            span: self.span_from(start),
+        })
+    }
+
+    /** `opaque type EmailAddress = String` */
+    fn parse_opaque_type_dec(&mut self) -> Result<TypeDec, ParserError> {
+        let start = self.current_span();
+        let _ = self.consume_expected(TokenKind::Opaque, "opaque keyword")?;
+        let type_dec = self.parse_type_dec()?;
+        if !type_dec.type_vars.is_empty() {
+            return Err(self.token_parser_error("opaque types can't have type vars"));
+        }
+        Ok(TypeDec {
+            opaque: true,
+            span: self.span_from(start),
+            ..type_dec
+        })
+    }
+
+    /** `{ key: value }` as opposed to a block, which can't start with `iden:` */
+    fn peek_for_record_literal(&self) -> bool {
+        if !matches!(self.peek_token_kind(), Some(TokenKind::LCurly)) {
+            return false;
+        }
+        let mut significant = self.tokens[self.current + 1..]
+            .iter()
+            .filter(|t| t.kind != TokenKind::NL);
+        matches!(
+            (significant.next(), significant.next()),
+            (Some(key), Some(colon)) if key.kind.is_identifier() && colon.kind == TokenKind::Colon
+        )
+    }
+
+    fn parse_record_literal(
+        &mut self,
+        type_identifier: Option<TypeIdentifier>,
+        start: Span,
+    ) -> Result<Expr, ParserError> {
+        let _ = self.consume_expected(TokenKind::LCurly, "opening curly of record")?;
+        let mut members = Vec::new();
+        self.swallow_lines();
+        while !self.peek_expected_kind(TokenKind::RCurly) {
+            let member_start = self.current_span();
+            let key = self.parse_identifier()?;
+            let _ = self.consume_expected(TokenKind::Colon, "colon after record key")?;
+            self.swallow_lines();
+            let value = self.parse_expr()?;
+            members.push(ObjectMember {
+                key,
+                value,
+                span: self.span_from(member_start),
+            });
+
+            // members are separated by commas and/or newlines
+            self.swallow_lines();
+            self.consume_if(|t| t.kind == TokenKind::Comma);
+            self.swallow_lines();
+        }
+        let _ = self.consume_expected(TokenKind::RCurly, "closing curly of record")?;
+
+        Ok(Expr::new(
+            ExprKind::Record(type_identifier, members, None),
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_record_type(&mut self) -> Result<TypeExpr, ParserError> {
+        let _ = self.consume_expected(TokenKind::LCurly, "opening curly of record type")?;
+        let mut members = Vec::new();
+        self.swallow_lines();
+        while !self.peek_expected_kind(TokenKind::RCurly) {
+            let member_start = self.current_span();
+            let identifier = self.parse_identifier()?;
+            let _ = self.consume_expected(TokenKind::Colon, "colon after record member name")?;
+            let type_expr = self.parse_type_expr()?;
+            members.push(RecordTypeMemeber {
+                identifier,
+                type_expr,
+                span: self.span_from(member_start),
+            });
+
+            self.swallow_lines();
+            self.consume_if(|t| t.kind == TokenKind::Comma);
+            self.swallow_lines();
+        }
+        let _ = self.consume_expected(TokenKind::RCurly, "closing curly of record type")?;
+        Ok(TypeExpr::Record(members))
+    }
+
+    fn parse_enum_dec(&mut self) -> Result<EnumDec, ParserError> {
+        let start = self.current_span();
+        let _ = self.consume_expected(TokenKind::Enum, "enum keyword")?;
+        let identifier = self.parse_type_identifier()?;
+        let type_vars = self.parse_type_vars()?;
+        // the README also writes `enum Foo = { ... }`
+        self.consume_if(|t| t.kind == TokenKind::Assign);
+        self.swallow_lines();
+        let _ = self.consume_expected(TokenKind::LCurly, "opening curly of enum")?;
+
+        let mut variants = Vec::new();
+        self.swallow_lines();
+        while !self.peek_expected_kind(TokenKind::RCurly) {
+            let variant_start = self.current_span();
+            let name = self.parse_type_identifier()?;
+            let mut params = Vec::new();
+            if self.consume_if(|t| t.kind == TokenKind::LParen).is_some() {
+                while !self.peek_expected_kind(TokenKind::RParen) {
+                    params.push(self.parse_type_expr()?);
+                    if self.consume_if(|t| t.kind == TokenKind::Comma).is_none() {
+                        break;
+                    }
+                }
+                let _ =
+                    self.consume_expected(TokenKind::RParen, "closing paren of variant payload")?;
+            }
+            variants.push(EnumVariant {
+                name,
+                params,
+                span: self.span_from(variant_start),
+            });
+
+            // variants are separated by commas and/or newlines
+            self.consume_if(|t| t.kind == TokenKind::Comma);
+            self.swallow_lines();
+        }
+        let _ = self.consume_expected(TokenKind::RCurly, "closing curly of enum")?;
+
+        if variants.is_empty() {
+            return Err(self.token_parser_error("enum needs at least one variant"));
+        }
+
+        Ok(EnumDec {
+            doc: None,
+            identifier,
+            type_vars,
+            variants,
+            span: self.span_from(start),
+        })
+    }
+
+    /** optional `<T, U>` after a type name */
+    fn parse_type_vars(&mut self) -> Result<Vec<TypeIdentifier>, ParserError> {
+        let mut type_vars = Vec::new();
+        if self.consume_if(|t| t.kind == TokenKind::LAngle).is_none() {
+            return Ok(type_vars);
+        }
+        while !self.peek_expected_kind(TokenKind::RAngle) {
+            type_vars.push(self.parse_type_identifier()?);
+            if self.consume_if(|t| t.kind == TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        let _ = self.consume_expected(TokenKind::RAngle, "closing angle of type vars")?;
+        Ok(type_vars)
+    }
+
     fn parse_extern(&mut self) -> Result<ExternPackage, ParserError> {
+        let start = self.current_span();
         let _ = self.consume_expected(TokenKind::Extern, "extern keyword")?;
         self.swallow_lines();
         let package_name = {
@@ -371,6 +900,7 @@ impl Parser {
             self.swallow_lines();
 
             // get the member identifier
+            let member_start = self.current_span();
             let local_identifier = self.parse_identifier()?;
 
             let _ = self.consume_expected(TokenKind::Colon, "colon after local name")?;
@@ -388,7 +918,7 @@ impl Parser {
                     // TODO: just getting the first segment here
                     Ok(type_identifier
                         .name
-                        .get(0)
+                        .first()
                         .cloned()
                         .expect("At least one name"))
                 }
@@ -411,6 +941,7 @@ impl Parser {
 
                     // parse parameters
                     while self.current < closing_paren_pos {
+                        let param_start = self.current_span();
                         let param_name = self.parse_identifier()?;
                         let _ =
                             self.consume_expected(TokenKind::Colon, "colon after param name")?;
@@ -418,11 +949,12 @@ impl Parser {
                         let param = FunctionParameter {
                             identifier: param_name,
                             type_expr: Some(param_type),
+                            span: self.span_from(param_start),
                         };
                         params.push(param);
 
                         self.swallow_lines();
-                        println!(
+                        eprintln!(
                             "extern param pos: {} of {}",
                             self.current, closing_paren_pos
                         );
@@ -441,6 +973,7 @@ impl Parser {
                         self.consume_expected(TokenKind::FatArrow, "fat arrow after parameters")?;
                     self.swallow_lines();
                     let return_type = self.parse_type_expr()?;
+                    let span = self.span_from(member_start);
 
                     // consume trailing comma
                     self.swallow_lines();
@@ -451,6 +984,7 @@ impl Parser {
                         external_name: identifier,
                         parameters: params,
                         return_type,
+                        span,
                     }
                 }
                 _ => {
@@ -469,6 +1003,7 @@ impl Parser {
         Ok(ExternPackage {
             package_name,
             definitions: members,
+            span: self.span_from(start),
         })
     }
 
@@ -476,86 +1011,120 @@ impl Parser {
         if self.peek_token().unwrap().kind != TokenKind::Const {
             return Err(self.token_parser_error("Expected const keyword"));
         }
+        let start = self.current_span();
         self.next_token(); // consume "const"
 
         let identifier = self.parse_identifier()?;
         let peek_token = self.peek_token().unwrap();
         let mut type_annotation: Option<TypeExpr> = None;
+        let mut type_annotation_span: Option<Span> = None;
 
         // check for optional type annotation
         if let TokenKind::Colon = peek_token.kind {
             self.next_token(); // consume ":"
+            let annotation_start = self.current_span();
             type_annotation = Some(self.parse_type_expr()?);
+            type_annotation_span = Some(self.span_from(annotation_start));
         }
 
         if self.peek_token().unwrap().kind != TokenKind::Assign {
             return Err(self.token_parser_error("Expected ="));
         }
         self.next_token(); // consume "="
+        self.swallow_lines();
+
+        let value = self.parse_expr()?;
 
         Ok(ConstDec {
+            doc: None,
             identifier,
             type_annotation,
-            value: Box::new(self.parse_expr()?),
+            type_annotation_span,
+            value: Box::new(value),
+            span: self.span_from(start),
         })
     }
 
     // TODO: Need to handle module name ref (e.g. Log.print)
     // "Log" comes in as a type identifier
     fn parse_iden_or_call(&mut self) -> Result<Expr, ParserError> {
-        println!("parse_iden_or_call");
+        eprintln!("parse_iden_or_call");
+        let start = self.current_span();
         let mixed_identifier = self.parse_mixed_identifier()?;
-        let value_ref = Expr::ValueReference(mixed_identifier.clone());
+        let value_ref = Expr::new(
+            ExprKind::ValueReference(mixed_identifier.clone(), None),
+            self.span_from(start),
+        );
         let mut expr = value_ref;
 
+        // named record literal, e.g. User { name: `Andrew` }
+        if let MixedIdentifier::TypeIdentifier(type_identifier) = mixed_identifier {
+            if self.peek_for_record_literal() {
+                expr = self.parse_record_literal(Some(type_identifier), start)?;
+            }
+        }
+
+        // explicit type args wait for the call they come before
+        let mut generic_args = Vec::new();
         while let Some(peek_token) = self.peek_token() {
             match peek_token.kind {
                 TokenKind::LParen => {
                     let mut args: Vec<Expr> = Vec::new();
                     let closing_paren_index =
                         self.find_matching_closing_paren(TokenKind::LParen, TokenKind::RParen)?;
-                    println!("closing index: {} {}", self.current, closing_paren_index);
+                    eprintln!("closing index: {} {}", self.current, closing_paren_index);
                     let _ = self.consume_expected(TokenKind::LParen, "opening paren");
 
                     while self.current < closing_paren_index {
-                        println!("parsing argument");
-                        args.push(self.parse_expr()?);
-                        println!("argument parsed");
+                        eprintln!("parsing argument");
+                        let arg_start = self.current_span();
+                        match self.parse_expr() {
+                            Ok(arg) => args.push(arg),
+                            Err(error) => {
+                                self.errors.push(error);
+                                self.synchronize(TokenKind::Comma, Some(closing_paren_index));
+                                args.push(Expr::new(ExprKind::Error, self.span_from(arg_start)));
+                            }
+                        }
+                        eprintln!("argument parsed");
                         self.swallow_lines();
-                        println!("position: {} {}", self.current, closing_paren_index);
+                        eprintln!("position: {} {}", self.current, closing_paren_index);
                         if self.current < closing_paren_index {
                             let _ = self.consume_expected(TokenKind::Comma, "comma separator")?;
                         }
                     }
                     let _ = self.consume_expected(TokenKind::RParen, "expected closing paren")?;
 
-                    expr = Expr::FunctionCall {
-                        callee: Box::new(expr),
-                        args,
-                        generic_args: Vec::new(),
-                    }
+                    expr = Expr::new(
+                        ExprKind::FunctionCall {
+                            callee: Box::new(expr),
+                            args,
+                            generic_args: std::mem::take(&mut generic_args),
+                        },
+                        self.span_from(start),
+                    )
+                }
+                TokenKind::LSquare => {
+                    let _ = self.consume_expected(TokenKind::LSquare, "opening square bracket");
+                    let index = self.parse_expr()?;
+                    let _ = self
+                        .consume_expected(TokenKind::RSquare, "closing square bracket of index")?;
+                    expr = Expr::new(
+                        ExprKind::Index(Box::new(expr), Box::new(index), None),
+                        self.span_from(start),
+                    );
                 }
                 TokenKind::Dot => {
                     let _ = self.consume_expected(TokenKind::Dot, "expected dot");
                     let rhs_iden = self.parse_identifier()?;
 
-                    expr = Expr::DotCall(Box::new(expr), rhs_iden);
+                    expr = Expr::new(
+                        ExprKind::DotCall(Box::new(expr), rhs_iden),
+                        self.span_from(start),
+                    );
                 }
-                TokenKind::LAngle => {
-                    // lookeahead, if not a TypeIdentifier, then it's a less than operator
-                    let double_peek_token_kind = self.peek_token_kind().unwrap();
-                    if double_peek_token_kind.is_type_identifier() {
-                        self.next_token(); // consume "<"
-                        let closing_angle_index =
-                            self.find_matching_closing_paren(TokenKind::LAngle, TokenKind::RAngle)?;
-                        let mut type_args: Vec<TypeExpr> = Vec::new();
-                        while self.current < closing_angle_index {
-                            type_args.push(self.parse_type_expr()?);
-                            self.consume_if(|t| t.kind == TokenKind::Comma);
-                        }
-                    } else {
-                        break;
-                    }
+                TokenKind::LAngle if self.peek_for_call_type_args() => {
+                    generic_args = self.parse_type_args()?;
                 }
                 _ => break,
             }
@@ -565,9 +1134,9 @@ impl Parser {
     }
 
     fn peek_for_fn_defition(&mut self) -> Result<bool, ParserError> {
-        println!("peeking for fn def");
+        eprintln!("peeking for fn def");
         if !self.peek_expected_kind(TokenKind::LParen) {
-            println!("not an lparen");
+            eprintln!("not an lparen");
             return Ok(false);
         }
         let close_paren = self.find_matching_closing_paren(TokenKind::LParen, TokenKind::RParen)?;
@@ -588,6 +1157,7 @@ impl Parser {
     }
 
     fn parse_fn_definition(&mut self) -> Result<Expr, ParserError> {
+        let start = self.current_span();
         let params_closing_paren_index =
             self.find_matching_closing_paren(TokenKind::LParen, TokenKind::RParen)?;
         self.next_token(); // consume "("
@@ -595,6 +1165,7 @@ impl Parser {
         let mut return_type: Option<TypeExpr> = None;
 
         while self.current < params_closing_paren_index {
+            let param_start = self.current_span();
             let identifier = self.parse_identifier()?;
             let mut type_anno: Option<TypeExpr> = None;
 
@@ -607,6 +1178,7 @@ impl Parser {
             parameters.push(FunctionParameter {
                 identifier,
                 type_expr: type_anno,
+                span: self.span_from(param_start),
             });
 
             match self.peek_token() {
@@ -626,23 +1198,26 @@ impl Parser {
 
         // check for return type anno
         if self.peek_token().unwrap().kind == TokenKind::Colon {
-            println!("fn def has type anno");
+            eprintln!("fn def has type anno");
             self.next_token(); // consume ":"
             return_type = Some(self.parse_type_expr()?);
         }
 
         let _ = self.consume_matching_expected(|t| t.kind == TokenKind::FatArrow, "=>")?;
-        println!("parsing fn body {:?}", self.peek_token());
+        eprintln!("parsing fn body {:?}", self.peek_token());
         let body = self.parse_expr()?;
-        println!("parsed fn body");
-
-        Ok(Expr::FunctionDefinition {
-            parameters,
-            return_type,
-            body: Box::new(body),
-            scope: None,
-            identifier: None,
-        })
+        eprintln!("parsed fn body");
+
+        Ok(Expr::new(
+            ExprKind::FunctionDefinition {
+                parameters,
+                return_type,
+                body: Box::new(body),
+                scope: None,
+                identifier: None,
+            },
+            self.span_from(start),
+        ))
     }
 
     fn parse_boolean_expr(&mut self) -> Result<Expr, ParserError> {
@@ -651,7 +1226,7 @@ impl Parser {
             "boolean literal",
         )?;
         if let TokenKind::Boolean(bool) = token.kind {
-            return Ok(Expr::Boolean(bool));
+            return Ok(Expr::new(ExprKind::Boolean(bool), token.span));
         }
         Err(self.token_parser_error("Unexpected issue parsing boolean literal"))
     }
@@ -662,7 +1237,7 @@ impl Parser {
             "number literal",
         )?;
         if let TokenKind::Number(number) = token.kind {
-            return Ok(Expr::Number(number.to_string()));
+            return Ok(Expr::new(ExprKind::Number(number.to_string()), token.span));
         }
         Err(self.token_parser_error("Unexpected issue parsing number"))
     }
@@ -673,11 +1248,92 @@ impl Parser {
             "string literal",
         )?;
         if let TokenKind::String(string) = token.kind {
-            return Ok(Expr::String(string));
+            return Ok(Expr::new(ExprKind::String(string), token.span));
         }
         Err(self.token_parser_error("Unexpected issue parsing string"))
     }
 
+    /** elements can go on their own lines, a trailing comma is fine */
+    fn parse_array_literal(&mut self) -> Result<Expr, ParserError> {
+        let start = self.current_span();
+        let _ = self.consume_expected(TokenKind::LSquare, "opening square bracket")?;
+        let mut elements = Vec::new();
+        self.swallow_lines();
+        while !self.peek_expected_kind(TokenKind::RSquare) {
+            elements.push(self.parse_expr()?);
+            self.swallow_lines();
+            if self.consume_if(|t| t.kind == TokenKind::Comma).is_none() {
+                break;
+            }
+            self.swallow_lines();
+        }
+        let _ = self.consume_expected(TokenKind::RSquare, "closing square bracket of array")?;
+        Ok(Expr::new(
+            ExprKind::Array(TypeExpr::InferenceRequired(None), elements),
+            self.span_from(start),
+        ))
+    }
+
+    /** the operand takes any ** after it, e.g. `-x ** 2` is `-(x ** 2)` */
+    fn parse_unary_expr(&mut self) -> Result<Expr, ParserError> {
+        let start = self.current_span();
+        let op = match self.next_token().map(|t| t.kind) {
+            Some(TokenKind::Minus) => UnaryOp::Negate,
+            Some(TokenKind::Bang) => UnaryOp::Not,
+            _ => return Err(self.token_parser_error("Expected - or !")),
+        };
+        let operand = self.parse_expr_with_precedence(POWER_PRECEDENCE)?;
+        Ok(Expr::new(
+            ExprKind::Unary(op, Box::new(operand)),
+            self.span_from(start),
+        ))
+    }
+
+    /** each ${} gets its own parser, it has to hold exactly one expression */
+    fn parse_template_expr(&mut self) -> Result<Expr, ParserError> {
+        let token = self.consume_matching_expected(
+            |t| matches!(t.kind, TokenKind::Template(_)),
+            "template string",
+        )?;
+        let TokenKind::Template(template_tokens) = token.kind else {
+            return Err(self.token_parser_error("Unexpected issue parsing template string"));
+        };
+
+        let mut parts = Vec::new();
+        for template_token in template_tokens {
+            match template_token {
+                TemplateToken::Text(text) if text.is_empty() => {}
+                TemplateToken::Text(text) => parts.push(TemplatePart::Text(text)),
+                TemplateToken::Interpolation(mut tokens) => {
+                    let Some(last_token) = tokens.last().cloned() else {
+                        return Err(ParserError {
+                            message: "Expected an expression inside ${}".to_string(),
+                            line_no: token.line_no,
+                            col_no: token.col_no,
+                            span: token.span,
+                        });
+                    };
+                    tokens.push(Token {
+                        kind: TokenKind::NL,
+                        span: Span::new(
+                            last_token.span.file_id,
+                            last_token.span.end,
+                            last_token.span.end,
+                        ),
+                        ..last_token
+                    });
+                    let mut parser = Parser::new(tokens);
+                    let expr = parser.parse_expr()?;
+                    if parser.peek_token_kind() != Some(TokenKind::NL) {
+                        return Err(parser.token_parser_error("Expected } after the expression"));
+                    }
+                    parts.push(TemplatePart::Expr(expr));
+                }
+            }
+        }
+        Ok(Expr::new(ExprKind::Template(parts), token.span))
+    }
+
     fn parse_return_statement(&mut self) -> Result<BlockStatement, ParserError> {
         let _return = self
             .consume_matching_expected(|t| matches!(t.kind, TokenKind::Return), "return keyword")?;
@@ -713,34 +1369,145 @@ impl Parser {
     }
 
     fn parse_type_expr(&mut self) -> Result<TypeExpr, ParserError> {
-        let peek_token = self.peek_token().unwrap();
+        let Some(peek_token) = self.peek_token() else {
+            return Err(self.token_parser_error("Expected a type"));
+        };
         let type_expr = match &peek_token.kind {
             TokenKind::TypeIdentifier(name) => match name.as_str() {
                 "String" => {
                     self.next_token(); // consume
                     TypeExpr::String
                 }
+                "Number" => {
+                    self.next_token(); // consume
+                    TypeExpr::Number
+                }
+                "Boolean" => {
+                    self.next_token(); // consume
+                    TypeExpr::Boolean
+                }
                 "Void" => {
                     self.next_token(); // consume
                     TypeExpr::Void
                 }
-                _ => TypeExpr::TypeRef(self.parse_type_identifier()?),
+                _ => {
+                    let type_identifier = self.parse_type_identifier()?;
+                    if self.peek_expected_kind(TokenKind::LAngle) {
+                        TypeExpr::GenericRef(type_identifier, self.parse_type_args()?)
+                    } else {
+                        TypeExpr::TypeRef(type_identifier)
+                    }
+                }
             },
-            _ => todo!(),
+            TokenKind::LCurly => self.parse_record_type()?,
+            TokenKind::LParen => self.parse_fn_type()?,
+            _ => return Err(self.token_parser_error("Expected a type")),
         };
-        Ok(type_expr)
+        Ok(self.parse_array_type_suffix(type_expr))
+    }
+
+    /** (Number, String) => Void, or a single type in parens, e.g. ((Number) => Void)[] */
+    fn parse_fn_type(&mut self) -> Result<TypeExpr, ParserError> {
+        let _ = self.consume_expected(TokenKind::LParen, "opening paren of function type")?;
+        let mut parameters = Vec::new();
+        while !self.peek_expected_kind(TokenKind::RParen) {
+            parameters.push(self.parse_type_expr()?);
+            if self.consume_if(|t| t.kind == TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        let _ = self.consume_expected(TokenKind::RParen, "closing paren of function type")?;
+
+        if !self.peek_expected_kind(TokenKind::FatArrow) {
+            return match <[TypeExpr; 1]>::try_from(parameters) {
+                Ok([grouped]) => Ok(grouped),
+                Err(_) => Err(self.token_parser_error("=> and a return type")),
+            };
+        }
+        self.next_token(); // consume "=>"
+        let return_type = self.parse_type_expr()?;
+        Ok(TypeExpr::FunctionDefinition {
+            type_identifier: TypeIdentifier { name: vec![] },
+            parameters,
+            return_type: Box::new(return_type),
+        })
+    }
+
+    /** `<Number, String>` after a generic type or before a call's args */
+    fn parse_type_args(&mut self) -> Result<Vec<TypeExpr>, ParserError> {
+        let _ = self.consume_expected(TokenKind::LAngle, "opening angle of type args")?;
+        let mut type_args = Vec::new();
+        while !self.peek_expected_kind(TokenKind::RAngle) {
+            type_args.push(self.parse_type_expr()?);
+            if self.consume_if(|t| t.kind == TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        let _ = self.consume_expected(TokenKind::RAngle, "closing angle of type args")?;
+        Ok(type_args)
+    }
+
+    /**
+     * Whether the < coming up starts a call's type args rather than being a
+     * less than, `f<Number>(x)` vs `a < B`. The closing > has to be on the same
+     * line and followed by the call's opening paren
+     */
+    fn peek_for_call_type_args(&self) -> bool {
+        let mut depth = 0;
+        for (index, token) in self.tokens.iter().enumerate().skip(self.current) {
+            match token.kind {
+                TokenKind::LAngle => depth += 1,
+                TokenKind::RAngle => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self
+                            .tokens
+                            .get(index + 1)
+                            .is_some_and(|t| t.kind == TokenKind::LParen);
+                    }
+                }
+                TokenKind::NL => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /** any number of [] after a type, e.g. Number[][] */
+    fn parse_array_type_suffix(&mut self, mut type_expr: TypeExpr) -> TypeExpr {
+        while self.peek_expected_kind(TokenKind::LSquare)
+            && self
+                .tokens
+                .get(self.current + 1)
+                .is_some_and(|t| t.kind == TokenKind::RSquare)
+        {
+            self.next_token();
+            self.next_token();
+            type_expr = TypeExpr::Array(Box::new(type_expr));
+        }
+        type_expr
     }
 
     fn parse_type_identifier(&mut self) -> Result<TypeIdentifier, ParserError> {
         if !self.peek_token().unwrap().kind.is_type_identifier() {
             return Err(self.token_parser_error("Expected type identifier"));
         }
-        let token = self.next_token().unwrap();
-        if let TokenKind::TypeIdentifier(name) = token.kind {
-            Ok(TypeIdentifier { name: vec![name] })
-        } else {
-            Err(self.token_parser_error("Unexpected error parsing type identifier"))
+        let mut names = Vec::new();
+        while let Some(TokenKind::TypeIdentifier(name)) = self.peek_token_kind() {
+            self.next_token();
+            names.push(name);
+
+            // dotted type names, e.g. Animal.Dog, but not Log.print
+            let next_is_type_identifier = self
+                .tokens
+                .get(self.current + 1)
+                .is_some_and(|t| t.kind.is_type_identifier());
+            if !(self.peek_expected_kind(TokenKind::Dot) && next_is_type_identifier) {
+                break;
+            }
+            self.next_token(); // consume "."
         }
+        Ok(TypeIdentifier { name: names })
     }
 
     pub fn consume_expected(
@@ -822,7 +1589,26 @@ impl Parser {
     }
 
     fn peek_token(&self) -> Option<&Token> {
-        self.tokens.get(self.current).clone()
+        self.tokens.get(self.current)
+    }
+
+    /** span of the next token, marks where the node about to be parsed starts */
+    fn current_span(&self) -> Span {
+        self.peek_token()
+            .or(self.tokens.last())
+            .map(|t| t.span)
+            .unwrap_or_default()
+    }
+
+    /** span from start up to the end of the last consumed token, ignoring newlines */
+    fn span_from(&self, start: Span) -> Span {
+        let end = self.tokens[..self.current]
+            .iter()
+            .rev()
+            .find(|t| t.kind != TokenKind::NL)
+            .map(|t| t.span.end)
+            .unwrap_or(start.end);
+        Span::new(start.file_id, start.start, end.max(start.start))
     }
 
     fn peek_token_kind(&self) -> Option<TokenKind> {
@@ -830,13 +1616,19 @@ impl Parser {
     }
 
     fn peek_expected_kind(&mut self, expected_kind: TokenKind) -> bool {
-        self.peek_token_kind()
-            .map_or(false, |kind| kind == expected_kind)
+        self.peek_token_kind() == Some(expected_kind)
+    }
+
+    fn peek_kind_past_lines(&self) -> Option<TokenKind> {
+        self.tokens[self.current..]
+            .iter()
+            .find(|t| t.kind != TokenKind::NL)
+            .map(|t| t.kind.clone())
     }
 
     fn peek_for_expr_continuation(&self) -> bool {
         let mut position = self.current;
-        println!("peeking pos {}", position);
+        eprintln!("peeking pos {}", position);
 
         // peek through any newlines
         while let Some(token) = self.tokens.get(position) {
@@ -852,7 +1644,7 @@ impl Parser {
             if let Some(peek_token) = self.tokens.get(position) {
                 let is_closing_syntax = matches!(
                     peek_token.kind,
-                    TokenKind::RCurly | TokenKind::RParen | TokenKind::RAngle | TokenKind::RSquare
+                    TokenKind::RCurly | TokenKind::RParen | TokenKind::RSquare
                 );
                 if is_closing_syntax {
                     return false;
@@ -871,7 +1663,14 @@ impl Parser {
                 TokenKind::LParen
                     | TokenKind::Dot
                     | TokenKind::LAngle
+                    | TokenKind::RAngle
+                    | TokenKind::LogicalAnd
+                    | TokenKind::LogicalOr
+                    | TokenKind::DoubleAsterix
+                    | TokenKind::Is
+                    | TokenKind::NotIs
                     | TokenKind::RPipe
+                    | TokenKind::LPipe
                     | TokenKind::FatArrow
                     | TokenKind::SkinnyArrow
                     | TokenKind::Plus
@@ -891,7 +1690,7 @@ impl Parser {
     fn next_token(&mut self) -> Option<Token> {
         let token = self.tokens.get(self.current).cloned();
         if token.is_some() {
-            println!("consumed {:?}", token.clone().unwrap());
+            eprintln!("consumed {:?}", token.clone().unwrap());
             self.current += 1;
         }
         token
@@ -909,6 +1708,21 @@ impl Parser {
         }
     }
 
+    /** consecutive `///` lines, joined up with the leading space of each removed */
+    fn parse_doc_comments(&mut self) -> Option<String> {
+        let mut lines = Vec::new();
+        while let Some(TokenKind::DocComment(text)) = self.peek_token_kind() {
+            lines.push(text.strip_prefix(' ').unwrap_or(&text).to_string());
+            self.next_token();
+            self.swallow_lines();
+        }
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+
     fn swallow_lines(&mut self) {
         while let Some(peek_token) = self.peek_token() {
             if peek_token.kind == TokenKind::NL {
@@ -926,11 +1740,11 @@ mod test {
 
     use super::*;
 
-    fn create_parse_tree(input: &str) -> Result<Program, ParserError> {
+    fn create_parse_tree(input: &str) -> Result<Program, Vec<ParserError>> {
         let mut lexer = Lexer::new(input.to_string());
         let tokens = lexer.tokenize();
         let mut parser = Parser::new(tokens);
-        return parser.parse();
+        parser.parse()
     }
 
     #[test]
@@ -945,6 +1759,573 @@ mod test {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_spans_are_recorded() {
+        let program = create_parse_tree("module Foo\nconst foo = 1 + 22").unwrap();
+        let TopStatement::ConstDec(const_dec) = &program.statements[0] else {
+            panic!("expected a const dec");
+        };
+        assert_eq!((const_dec.span.start, const_dec.span.end), (11, 29));
+        assert_eq!(
+            (const_dec.value.span.start, const_dec.value.span.end),
+            (23, 29)
+        );
+
+        let ExprKind::Binary(lhs, _, rhs) = &const_dec.value.kind else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!((lhs.span.start, lhs.span.end), (23, 24));
+        assert_eq!((rhs.span.start, rhs.span.end), (27, 29));
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let program = create_parse_tree(
+            "module Foo
+            const word = match (count) {
+                1 -> `one`
+                2 ->
+                    `two`
+                other -> {
+                    const many = `many`
+                    many
+                }
+            }",
+        )
+        .unwrap();
+        let TopStatement::ConstDec(const_dec) = &program.statements[0] else {
+            panic!("expected a const dec");
+        };
+        let ExprKind::Match(subject, clauses, _) = &const_dec.value.kind else {
+            panic!("expected a match expression");
+        };
+
+        assert!(matches!(subject.kind, ExprKind::ValueReference(..)));
+        let patterns: Vec<Pattern> = clauses.iter().map(|c| c.pattern.clone()).collect();
+        assert_eq!(
+            patterns,
+            vec![
+                Pattern::Number("1".to_string()),
+                Pattern::Number("2".to_string()),
+                Pattern::ValueRef(Identifier {
+                    name: "other".to_string()
+                }),
+            ]
+        );
+        assert!(matches!(
+            clauses[2].body.kind,
+            ExprKind::BlockExpression(ref statements, _) if statements.len() == 2
+        ));
+    }
+
+    #[test]
+    fn test_if_else_chain() {
+        let program = create_parse_tree(
+            "module Foo
+            const size = if count >= 10 {
+                `big`
+            } else if count >= 5 {
+                `medium`
+            }
+            else {
+                `small`
+            }",
+        )
+        .unwrap();
+        let TopStatement::ConstDec(const_dec) = &program.statements[0] else {
+            panic!("expected a const dec");
+        };
+        let ExprKind::IfElse(condition, true_branch, false_branch, _) = &const_dec.value.kind
+        else {
+            panic!("expected an if expression");
+        };
+
+        assert!(matches!(
+            condition.kind,
+            ExprKind::Binary(_, BinaryOp::GreaterOrEqual, _)
+        ));
+        assert!(matches!(true_branch.kind, ExprKind::BlockExpression(_, _)));
+        let ExprKind::IfElse(_, _, else_branch, _) = &false_branch.kind else {
+            panic!("expected else if to nest another if expression");
+        };
+        assert!(matches!(else_branch.kind, ExprKind::BlockExpression(_, _)));
+    }
+
+    #[test]
+    fn test_if_requires_else() {
+        let result = create_parse_tree(
+            "module Foo
+            const size = if big { `big` }",
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_recovers_after_syntax_errors() {
+        let tokens = Lexer::new(
+            "module Foo
+            const a = )
+            const b = 1
+            const f = () => {
+                const x = ]
+                x
+            }
+            const g = f(1, ], 3)"
+                .to_string(),
+        )
+        .tokenize();
+        let (program, errors) = Parser::new(tokens).parse_partial();
+
+        assert_eq!(errors.len(), 3, "{:#?}", errors);
+        assert!(matches!(program.statements[0], TopStatement::Error(_)));
+        assert!(matches!(program.statements[1], TopStatement::ConstDec(_)));
+        let TopStatement::ConstDec(f) = &program.statements[2] else {
+            panic!("expected f to still parse");
+        };
+        let ExprKind::FunctionDefinition { body, .. } = &f.value.kind else {
+            panic!("expected a function");
+        };
+        assert!(matches!(
+            body.kind,
+            ExprKind::BlockExpression(ref statements, _) if matches!(
+                statements.as_slice(),
+                [BlockStatement::Expr(Expr { kind: ExprKind::Error, .. }), BlockStatement::Expr(_)]
+            )
+        ));
+        let TopStatement::ConstDec(g) = &program.statements[3] else {
+            panic!("expected g to still parse");
+        };
+        assert!(matches!(
+            g.value.kind,
+            ExprKind::FunctionCall { ref args, .. } if args.len() == 3 && args[1].kind == ExprKind::Error
+        ));
+    }
+
+    #[test]
+    fn test_doc_comments_attach_to_declarations() {
+        let program = create_parse_tree(
+            "module Foo
+            // not documentation
+            /// How many there are.
+            ///  Indented second line
+            const count = 1 // trailing comment
+            /// A user
+            type User = { name: String }
+            //// not documentation either
+            enum Pet { Cat }",
+        )
+        .unwrap();
+
+        let docs: Vec<Option<String>> = program
+            .statements
+            .iter()
+            .map(|statement| match statement {
+                TopStatement::ConstDec(const_dec) => const_dec.doc.clone(),
+                TopStatement::TypeDec(type_dec) => type_dec.doc.clone(),
+                TopStatement::EnumDec(enum_dec) => enum_dec.doc.clone(),
+                _ => panic!("unexpected statement"),
+            })
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                Some("How many there are.\n Indented second line".to_string()),
+                Some("A user".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let expressions = [
+            (
+                "a || b && c == d < e + f * g ** h",
+                "a || (b && (c == (d < (e + (f * (g ** h))))))",
+            ),
+            ("a - b - c", "(a - b) - c"),
+            ("2 ** 3 ** 2", "2 ** (3 ** 2)"),
+            ("-x ** 2", "-(x ** 2)"),
+            ("-a * b", "(-a) * b"),
+            ("!a && !b || c", "((!a) && (!b)) || c"),
+            ("a > b != c <= d", "(a > b) != (c <= d)"),
+        ];
+        for (implicit, explicit) in expressions {
+            let implicit_program = create_parse_tree(&format!("module Foo\n{}", implicit)).unwrap();
+            let explicit_program = create_parse_tree(&format!("module Foo\n{}", explicit)).unwrap();
+            assert_eq!(
+                implicit_program.statements, explicit_program.statements,
+                "{}",
+                implicit
+            );
+        }
+    }
+
+    #[test]
+    fn test_pipes_desugar_to_calls() {
+        let pipelines = [
+            (
+                "xs |> Array.map(double) |> sum",
+                "sum(Array.map(xs, double))",
+            ),
+            ("f <| g <| x", "f(g(x))"),
+            ("f <| x |> g", "f(g(x))"),
+            ("a + 1 |> f", "f(a + 1)"),
+            ("xs\n  |> double\n  |> add(1)", "add(double(xs), 1)"),
+        ];
+        for (piped, called) in pipelines {
+            let piped_program = create_parse_tree(&format!("module Foo\n{}", piped)).unwrap();
+            let called_program = create_parse_tree(&format!("module Foo\n{}", called)).unwrap();
+            assert_eq!(
+                piped_program.statements, called_program.statements,
+                "{}",
+                piped
+            );
+        }
+    }
+
+    #[test]
+    fn test_template_string() {
+        let program = create_parse_tree(
+            "module Foo
+            `Hello ${name}, ${count + 1}!`",
+        )
+        .unwrap();
+
+        let TopStatement::Expr(Expr {
+            kind: ExprKind::Template(parts),
+            ..
+        }) = &program.statements[0]
+        else {
+            panic!("expected a template string");
+        };
+        assert!(matches!(
+            parts.as_slice(),
+            [
+                TemplatePart::Text(hello),
+                TemplatePart::Expr(Expr { kind: ExprKind::ValueReference(..), .. }),
+                TemplatePart::Text(comma),
+                TemplatePart::Expr(Expr { kind: ExprKind::Binary(_, BinaryOp::Add, _), .. }),
+                TemplatePart::Text(bang),
+            ] if hello == "Hello " && comma == ", " && bang == "!"
+        ));
+
+        assert!(create_parse_tree("module Foo\n`${}`").is_err());
+        assert!(create_parse_tree("module Foo\n`${a b}`").is_err());
+    }
+
+    #[test]
+    fn test_enum_declaration() {
+        let program = create_parse_tree(
+            "module Foo
+            enum Animal {
+                Dog(Color),
+                Fish(Number, String)
+                Cat
+            }
+            const lady = Animal.Dog(Color.White)",
+        )
+        .unwrap();
+        let TopStatement::EnumDec(enum_dec) = &program.statements[0] else {
+            panic!("expected an enum dec");
+        };
+        let variant_names: Vec<Vec<String>> = enum_dec
+            .variants
+            .iter()
+            .map(|v| v.name.name.clone())
+            .collect();
+        assert_eq!(variant_names, vec![vec!["Dog"], vec!["Fish"], vec!["Cat"]]);
+        assert_eq!(enum_dec.variants[1].params.len(), 2);
+
+        let TopStatement::ConstDec(const_dec) = &program.statements[1] else {
+            panic!("expected a const dec");
+        };
+        let ExprKind::FunctionCall { callee, .. } = &const_dec.value.kind else {
+            panic!("expected a constructor call");
+        };
+        assert_eq!(
+            callee.kind,
+            ExprKind::ValueReference(
+                MixedIdentifier::TypeIdentifier(TypeIdentifier {
+                    name: vec!["Animal".to_string(), "Dog".to_string()]
+                }),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn test_variant_patterns() {
+        let program = create_parse_tree(
+            "module Foo
+            const sound = match animal {
+                Animal.Dog(color) -> `woof`
+                Animal.Cat -> `meow`
+            }",
+        )
+        .unwrap();
+        let TopStatement::ConstDec(const_dec) = &program.statements[0] else {
+            panic!("expected a const dec");
+        };
+        let ExprKind::Match(_, clauses, _) = &const_dec.value.kind else {
+            panic!("expected a match expression");
+        };
+        let animal = |variant: &str| TypeIdentifier {
+            name: vec!["Animal".to_string(), variant.to_string()],
+        };
+        assert_eq!(
+            clauses[0].pattern,
+            Pattern::Variant(
+                animal("Dog"),
+                vec![Identifier {
+                    name: "color".to_string()
+                }]
+            )
+        );
+        assert_eq!(clauses[1].pattern, Pattern::Variant(animal("Cat"), vec![]));
+    }
+
+    #[test]
+    fn test_arrays_and_indexing() {
+        let program = create_parse_tree(
+            "module Foo
+            type Grid = Number[][]
+            const xs: String[] = [
+                `a`,
+                `b`,
+            ]
+            const x = xs[1][0]",
+        )
+        .unwrap();
+
+        let TopStatement::TypeDec(type_dec) = &program.statements[0] else {
+            panic!("expected a type dec");
+        };
+        assert_eq!(
+            type_dec.type_val,
+            TypeExpr::Array(Box::new(TypeExpr::Array(Box::new(TypeExpr::Number))))
+        );
+        let TopStatement::ConstDec(xs) = &program.statements[1] else {
+            panic!("expected a const dec");
+        };
+        assert_eq!(
+            xs.type_annotation,
+            Some(TypeExpr::Array(Box::new(TypeExpr::String)))
+        );
+        assert!(matches!(&xs.value.kind, ExprKind::Array(_, elements) if elements.len() == 2));
+        let TopStatement::ConstDec(x) = &program.statements[2] else {
+            panic!("expected a const dec");
+        };
+        let ExprKind::Index(inner, _, None) = &x.value.kind else {
+            panic!("expected an index");
+        };
+        assert!(matches!(inner.kind, ExprKind::Index(..)));
+    }
+
+    #[test]
+    fn test_boolean_type_annotation() {
+        let program = create_parse_tree(
+            "module Foo
+            const flags: Boolean[] = [true, false]",
+        )
+        .unwrap();
+        let TopStatement::ConstDec(flags) = &program.statements[0] else {
+            panic!("expected a const dec");
+        };
+        assert_eq!(
+            flags.type_annotation,
+            Some(TypeExpr::Array(Box::new(TypeExpr::Boolean)))
+        );
+    }
+
+    #[test]
+    fn test_opaque_type_dec() {
+        let program = create_parse_tree(
+            "module Foo
+            opaque type EmailAddress = String
+            type Name = String",
+        )
+        .unwrap();
+        let TopStatement::TypeDec(email) = &program.statements[0] else {
+            panic!("expected a type dec");
+        };
+        assert!(email.opaque);
+        assert_eq!(email.type_val, TypeExpr::String);
+        let TopStatement::TypeDec(name) = &program.statements[1] else {
+            panic!("expected a type dec");
+        };
+        assert!(!name.opaque);
+
+        assert!(create_parse_tree(
+            "module Foo
+            opaque type Id<T> = T"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_generic_types_and_type_args() {
+        let program = create_parse_tree(
+            "module Foo
+            type Box<T> = { value: T }
+            const b: Box<Box<Number>> = make<Number>(1)
+            const lt = a < B.c",
+        )
+        .unwrap();
+
+        let TopStatement::TypeDec(box_dec) = &program.statements[0] else {
+            panic!("expected a type dec");
+        };
+        assert_eq!(box_dec.type_vars.len(), 1);
+        let TopStatement::ConstDec(b) = &program.statements[1] else {
+            panic!("expected a const dec");
+        };
+        let generic = |name: &str, type_args: Vec<TypeExpr>| {
+            TypeExpr::GenericRef(
+                TypeIdentifier {
+                    name: vec![name.to_string()],
+                },
+                type_args,
+            )
+        };
+        assert_eq!(
+            b.type_annotation,
+            Some(generic("Box", vec![generic("Box", vec![TypeExpr::Number])]))
+        );
+        let ExprKind::FunctionCall { generic_args, .. } = &b.value.kind else {
+            panic!("expected a call");
+        };
+        assert_eq!(*generic_args, vec![TypeExpr::Number]);
+
+        let TopStatement::ConstDec(lt) = &program.statements[2] else {
+            panic!("expected a const dec");
+        };
+        assert!(matches!(
+            lt.value.kind,
+            ExprKind::Binary(_, BinaryOp::LessThan, _)
+        ));
+    }
+
+    #[test]
+    fn test_function_types() {
+        let program = create_parse_tree(
+            "module Foo
+            type Handler = (Request, Number) => Response
+            type Callbacks = (() => Void)[]
+            const apply = (f: (Number) => String, x: Number) => f(x)",
+        )
+        .unwrap();
+
+        let fn_type =
+            |parameters: Vec<TypeExpr>, return_type: TypeExpr| TypeExpr::FunctionDefinition {
+                type_identifier: TypeIdentifier { name: vec![] },
+                parameters,
+                return_type: Box::new(return_type),
+            };
+        let type_ref = |name: &str| {
+            TypeExpr::TypeRef(TypeIdentifier {
+                name: vec![name.to_string()],
+            })
+        };
+        let TopStatement::TypeDec(handler) = &program.statements[0] else {
+            panic!("expected a type dec");
+        };
+        assert_eq!(
+            handler.type_val,
+            fn_type(
+                vec![type_ref("Request"), TypeExpr::Number],
+                type_ref("Response")
+            )
+        );
+        let TopStatement::TypeDec(callbacks) = &program.statements[1] else {
+            panic!("expected a type dec");
+        };
+        assert_eq!(
+            callbacks.type_val,
+            TypeExpr::Array(Box::new(fn_type(vec![], TypeExpr::Void)))
+        );
+        let TopStatement::ConstDec(apply) = &program.statements[2] else {
+            panic!("expected a const dec");
+        };
+        let ExprKind::FunctionDefinition { parameters, .. } = &apply.value.kind else {
+            panic!("expected a function");
+        };
+        assert_eq!(
+            parameters[0].type_expr,
+            Some(fn_type(vec![TypeExpr::Number], TypeExpr::String))
+        );
+        assert_eq!(parameters[1].type_expr, Some(TypeExpr::Number));
+    }
+
+    #[test]
+    fn test_variant_tests() {
+        let program = create_parse_tree(
+            "module Foo
+            const a = animal is Animal.Dog && animal !is Animal.Cat",
+        )
+        .unwrap();
+        let TopStatement::ConstDec(const_dec) = &program.statements[0] else {
+            panic!("expected a const dec");
+        };
+        let ExprKind::Binary(lhs, BinaryOp::And, rhs) = &const_dec.value.kind else {
+            panic!("expected is to bind tighter than &&");
+        };
+        let variant_test = |expr: &Expr| match &expr.kind {
+            ExprKind::Is {
+                variant, negated, ..
+            } => (variant.name.join("."), *negated),
+            kind => panic!("expected an is, got {:?}", kind),
+        };
+        assert_eq!(variant_test(lhs), ("Animal.Dog".to_string(), false));
+        assert_eq!(variant_test(rhs), ("Animal.Cat".to_string(), true));
+
+        // the bang of !is can't be split from it, and isDog is just a name
+        assert!(create_parse_tree("module Foo\nanimal ! is Animal.Cat").is_err());
+        assert!(create_parse_tree("module Foo\nconst a = !isDog").is_ok());
+    }
+
+    #[test]
+    fn test_record_types_and_literals() {
+        let program = create_parse_tree(
+            "module Foo
+            type User = {
+                name: String,
+                age: Number,
+            }
+            const bob = User { name: `Bob`, age: 42 }
+            const me = {
+                name: `Octocat`
+                age: 12
+            }
+            const block = { bob }",
+        )
+        .unwrap();
+        let TopStatement::TypeDec(type_dec) = &program.statements[0] else {
+            panic!("expected a type dec");
+        };
+        let TypeExpr::Record(members) = &type_dec.type_val else {
+            panic!("expected a record type");
+        };
+        assert_eq!(members.len(), 2);
+
+        let values: Vec<&ExprKind> = program.statements[1..]
+            .iter()
+            .map(|statement| match statement {
+                TopStatement::ConstDec(const_dec) => &const_dec.value.kind,
+                _ => panic!("expected a const dec"),
+            })
+            .collect();
+        assert!(matches!(
+            values[0],
+            ExprKind::Record(Some(_), members, _) if members.len() == 2
+        ));
+        assert!(matches!(
+            values[1],
+            ExprKind::Record(None, members, _) if members.len() == 2
+        ));
+        assert!(matches!(values[2], ExprKind::BlockExpression(_, _)));
+    }
+
     #[test]
     fn test_jumbo_syntax_does_parse() {
         let tests = [
@@ -976,14 +2357,14 @@ mod test {
                     ]",
             ),
             // IMPORT
-            ("single import", "import Browser.Dom expose (fetch, header)"),
-            ("expose as", "import Browser.Html expose as h"),
+            ("single import", "from Browser.Dom"),
+            ("import as", "from Browser.Html as H"),
             (
                 "big import",
-                "import Browser expose (window, DomElement)
-                 import Net.Http expose (Request, Response)
-                 import Browser.Html expose as h
-                 import Foo expose (bar)",
+                "from Browser
+                 from Net.Http
+                 from Browser.Html as H
+                 from Foo",
             ),
             // FUNCTION DEFINTIONS
             ("simple function", "() => {}"),
@@ -1070,10 +2451,8 @@ mod test {
         for (name, source) in tests {
             let moduled_source = format!("module Testing\n{}", source);
             let result = create_parse_tree(&moduled_source);
-            if result.is_ok() {
-                assert!(true, "{}", name);
-            } else {
-                panic!("{} {:?}", name, result.unwrap_err());
+            if let Err(err) = result {
+                panic!("{} {:?}", name, err);
             }
         }
     }
@@ -1108,14 +2487,14 @@ mod test {
                     ]",
             ),
             // IMPORT
-            ("single import", "import Browser.Dom expose (fetch, header)"),
-            ("expose as", "import Browser.Html expose as h"),
+            ("single import", "from Browser.Dom"),
+            ("import as", "from Browser.Html as H"),
             (
                 "big import",
-                "import Browser expose (window, DomElement)
-                 import Net.Http expose (Request, Response)
-                 import Browser.Html expose as h
-                 import Foo expose (bar)",
+                "from Browser
+                 from Net.Http
+                 from Browser.Html as H
+                 from Foo",
             ),
             // IF/ELSE
             (
@@ -1214,10 +2593,8 @@ mod test {
         for (name, source) in tests {
             let moduled_source = format!("module Testing\n{}", source);
             let result = create_parse_tree(&moduled_source);
-            if result.is_ok() {
-                assert!(true, "{}", name);
-            } else {
-                panic!("{} {:?}", name, result.unwrap_err());
+            if let Err(err) = result {
+                panic!("{} {:?}", name, err);
             }
         }
     }
@@ -33,6 +33,13 @@ pub struct TypeSymbol {
 #[derive(Debug, Clone)]
 pub struct ScopeTree {
     pub scopes: Vec<Scope>,
+    /** mistakes found while binding, binding carries on past them */
+    pub errors: Vec<CompilerError>,
+    /**
+     * Types written in the program and where, their names are looked up once
+     * the whole program is bound since types can be used before they're declared
+     */
+    type_uses: Vec<(TypeExpr, usize, Span)>,
     module_map: Arc<RwLock<ModuleMap>>,
     next_type_var: usize,
     next_fn: usize,
@@ -47,6 +54,8 @@ impl ScopeTree {
                 parent: None,
                 children: Vec::new(),
             }],
+            errors: Vec::new(),
+            type_uses: Vec::new(),
             module_map,
             next_type_var: 0,
             next_fn: 0,
@@ -76,35 +85,13 @@ impl ScopeTree {
         child_index
     }
 
-    pub fn apply_substitutions(&mut self) {
-        let mut updates = Vec::new();
-        let scopes = self.scopes.clone();
-
-        for (scope_index, scope) in scopes.iter().enumerate() {
-            println!("applying in scope {}", scope_index);
-            for (key, value) in &scope.value_symbols {
-                let type_expr = &value.type_expr;
-                let resolved_type = self.resolve_type(type_expr.clone(), scope_index);
-                updates.push((scope_index, key, resolved_type))
-            }
-        }
-
-        for (scope_index, key, resolved_type) in updates {
-            if let Some(scope) = self.scopes.get_mut(scope_index) {
-                if let Some(symbol) = scope.value_symbols.get_mut(key) {
-                    symbol.type_expr = resolved_type;
-                }
-            }
-        }
-    }
-
     pub fn bind_program(&mut self, program: Program) -> Result<Program, CompilerError> {
         let program_scope_index = self.new_program_scope();
         for import in program.imports.clone() {
             self.process_import(program_scope_index, import);
         }
 
-        Ok(Program {
+        let program = Program {
             scope: Some(program_scope_index),
             imports: program.imports,
             statements: program
@@ -121,15 +108,100 @@ impl ScopeTree {
                         TopStatement::Expr(expr) => TopStatement::Expr(
                             self.bind_expression(program_scope_index, expr.clone()),
                         ),
-                        TopStatement::EnumDec(_) => todo!(),
+                        TopStatement::EnumDec(enum_dec) => TopStatement::EnumDec(
+                            self.bind_enum_dec(program_scope_index, enum_dec.clone()),
+                        ),
                         TopStatement::ExternDec(extern_dec) => TopStatement::ExternDec(
                             self.bind_extern_dec(program_scope_index, extern_dec.clone()),
                         ),
+                        TopStatement::Error(span) => TopStatement::Error(*span),
                     }
                 })
                 .collect(),
             ..program
-        })
+        };
+
+        for (type_expr, scope_index, span) in std::mem::take(&mut self.type_uses) {
+            let mut undefined = Vec::new();
+            self.undefined_type_names(&type_expr, scope_index, &mut undefined);
+            for name in undefined {
+                self.errors
+                    .push(CompilerError::UndefinedName { name, span });
+            }
+        }
+        CompilerError::from_errors(std::mem::take(&mut self.errors))?;
+        Ok(program)
+    }
+
+    /** names in a written type that aren't declared types */
+    fn undefined_type_names(
+        &self,
+        type_expr: &TypeExpr,
+        scope_index: usize,
+        names: &mut Vec<String>,
+    ) {
+        match type_expr {
+            TypeExpr::TypeRef(type_identifier) | TypeExpr::GenericRef(type_identifier, _) => {
+                let name = type_identifier.name.join(".");
+                if self
+                    .find_type_symbol(scope_index, type_identifier.clone())
+                    .is_none()
+                    && !names.contains(&name)
+                {
+                    names.push(name);
+                }
+                if let TypeExpr::GenericRef(_, type_args) = type_expr {
+                    for type_arg in type_args {
+                        self.undefined_type_names(type_arg, scope_index, names);
+                    }
+                }
+            }
+            TypeExpr::FunctionDefinition {
+                parameters,
+                return_type,
+                ..
+            } => {
+                for param in parameters {
+                    self.undefined_type_names(param, scope_index, names);
+                }
+                self.undefined_type_names(return_type, scope_index, names);
+            }
+            TypeExpr::Record(members) => {
+                for member in members {
+                    self.undefined_type_names(&member.type_expr, scope_index, names);
+                }
+            }
+            TypeExpr::Array(element_type) => {
+                self.undefined_type_names(element_type, scope_index, names)
+            }
+            _ => {}
+        }
+    }
+
+    /** like create_value_symbol, but a redeclaration is reported and binding carries on */
+    fn declare_value(&mut self, scope_index: usize, name: String, type_expr: TypeExpr, span: Span) {
+        if let Err(error) = self
+            .create_value_symbol(scope_index, name, type_expr, span)
+            .map(|_| ())
+        {
+            self.errors.push(error);
+        }
+    }
+
+    /** like create_type_symbol, but a redeclaration is reported and binding carries on */
+    fn declare_type(
+        &mut self,
+        scope_index: usize,
+        identifier: TypeIdentifier,
+        type_expr: TypeExpr,
+        span: Span,
+    ) {
+        if let Err(error) = self
+            .create_type_symbol(scope_index, identifier, type_expr, span)
+            .map(|_| ())
+        {
+            self.errors.push(error);
+        }
     }
 
     fn bind_extern_dec(
@@ -141,17 +213,19 @@ impl ScopeTree {
             package_name: extern_package.clone().package_name,
             members: extern_package.clone().definitions,
         };
-        self.create_value_symbol(
+        self.declare_value(
             scope_index,
             extern_package.clone().package_name,
             extern_type.clone(),
+            extern_package.span,
         );
-        self.create_type_symbol(
+        self.declare_type(
             scope_index,
             TypeIdentifier {
                 name: vec![extern_package.clone().package_name],
             },
             extern_type,
+            extern_package.span,
         );
         extern_package
     }
@@ -167,47 +241,262 @@ impl ScopeTree {
         );
         let module_indexes = {
             let module_map = self.module_map.read().expect("can read module_map");
-            module_map
-                .find_modules_by_name(joined_name.as_str())
-                .expect("module should exist")
+            module_map.find_modules_by_name(joined_name.as_str())
+        };
+        let Some(module_indexes) = module_indexes else {
+            self.errors.push(CompilerError::UnknownModule {
+                name: joined_name,
+                span: import.span,
+            });
+            return;
         };
 
         let type_expr = TypeExpr::ImportRef(joined_name, module_indexes);
-        self.create_value_symbol(program_scope_index, scope_name, type_expr);
+        self.declare_value(program_scope_index, scope_name, type_expr, import.span);
     }
 
     pub fn bind_const_dec(&mut self, scope_index: usize, const_dec: ConstDec) -> ConstDec {
         let const_type = match const_dec.type_annotation.clone() {
-            Some(type_expr) => type_expr,
-            None => self.create_type_var(scope_index),
+            Some(type_expr) => {
+                self.type_uses.push((
+                    type_expr.clone(),
+                    scope_index,
+                    const_dec.type_annotation_span.unwrap_or(const_dec.span),
+                ));
+                type_expr
+            }
+            None => self.create_type_var(),
         };
-        self.create_value_symbol(scope_index, const_dec.identifier.clone().name, const_type);
+        self.declare_value(
+            scope_index,
+            const_dec.identifier.clone().name,
+            const_type,
+            const_dec.span,
+        );
         let value = self.bind_expression(scope_index, *const_dec.value.clone());
 
         ConstDec {
+            doc: const_dec.doc.clone(),
             value: Box::new(value),
             identifier: const_dec.identifier.clone(),
             type_annotation: const_dec.type_annotation.clone(),
+            type_annotation_span: const_dec.type_annotation_span,
+            span: const_dec.span,
         }
     }
 
+    /**
+     * Generic type decs are declared as a scheme over their type vars, uses
+     * like Box<Number> substitute the args in when they're resolved
+     *
+     * Opaque types don't resolve to their underlying type, so values go in and
+     * out through the value symbols `EmailAddress` and `EmailAddress.unwrap`
+     */
     pub fn bind_type_dec(&mut self, scope_index: usize, type_dec: TypeDec) -> TypeDec {
-        self.create_type_symbol(
+        if type_dec.opaque {
+            let opaque_type = TypeExpr::TypeRef(type_dec.identifier.clone());
+            let mut unwrap_identifier = type_dec.identifier.clone();
+            unwrap_identifier.name.push("unwrap".to_string());
+            self.declare_type(
+                scope_index,
+                type_dec.identifier.clone(),
+                TypeExpr::Opaque(
+                    type_dec.identifier.clone(),
+                    Box::new(type_dec.type_val.clone()),
+                ),
+                type_dec.span,
+            );
+            self.declare_value(
+                scope_index,
+                type_dec.identifier.name.join("."),
+                TypeExpr::FunctionDefinition {
+                    type_identifier: type_dec.identifier.clone(),
+                    parameters: vec![type_dec.type_val.clone()],
+                    return_type: Box::new(opaque_type.clone()),
+                },
+                type_dec.span,
+            );
+            self.declare_value(
+                scope_index,
+                unwrap_identifier.name.join("."),
+                TypeExpr::FunctionDefinition {
+                    type_identifier: unwrap_identifier,
+                    parameters: vec![opaque_type],
+                    return_type: Box::new(type_dec.type_val.clone()),
+                },
+                type_dec.span,
+            );
+            return type_dec;
+        }
+
+        // the type vars are only names inside the type dec
+        let type_vars: HashMap<String, TypeExpr> = type_dec
+            .type_vars
+            .iter()
+            .map(|type_var| (type_var.name.join("."), TypeExpr::InferenceRequired(None)))
+            .collect();
+        self.type_uses.push((
+            substitute_type_vars(type_dec.type_val.clone(), &type_vars),
+            scope_index,
+            type_dec.span,
+        ));
+        let type_expr = if type_dec.type_vars.is_empty() {
+            type_dec.type_val.clone()
+        } else {
+            TypeExpr::Scheme {
+                type_vars: type_dec.type_vars.clone(),
+                type_expr: Box::new(type_dec.type_val.clone()),
+            }
+        };
+        self.declare_type(
             scope_index,
             type_dec.identifier.clone(),
-            type_dec.clone().type_val,
-        );
-        if !type_dec.clone().type_vars.is_empty() {
-            let type_dec_scope_index = self.new_child_scope(scope_index);
-            for type_var in type_dec.clone().type_vars {
-                self.create_type_symbol(
-                    type_dec_scope_index,
-                    type_var.clone(),
-                    TypeExpr::InferenceRequired(Some(type_var)),
-                );
+            type_expr,
+            type_dec.span,
+        );
+        type_dec
+    }
+
+    /**
+     * Registers the enum as a type symbol and each variant as a value symbol
+     * named like `Animal.Dog`. Variants with a payload are constructor
+     * functions, the rest are plain values of the variant's type. Variant
+     * names are also type symbols so they can be used in annotations.
+     *
+     * Type vars are replaced with fresh inference vars, and constructors are
+     * generalized over them so each use can pick its own type args. Values of
+     * a generic enum have a type like Option<t0>, so the args follow them
+     */
+    pub fn bind_enum_dec(&mut self, scope_index: usize, enum_dec: EnumDec) -> EnumDec {
+        // scheme vars keep the declared order so explicit type args line up
+        let fresh_vars: Vec<TypeExpr> = enum_dec
+            .type_vars
+            .iter()
+            .map(|_| self.create_type_var())
+            .collect();
+        let type_args: HashMap<String, TypeExpr> = enum_dec
+            .type_vars
+            .iter()
+            .map(|type_var| type_var.name.join("."))
+            .zip(fresh_vars.iter().cloned())
+            .collect();
+        let scheme_vars: Vec<TypeIdentifier> = fresh_vars
+            .iter()
+            .filter_map(|type_arg| match type_arg {
+                TypeExpr::InferenceRequired(Some(type_var)) => Some(type_var.clone()),
+                _ => None,
+            })
+            .collect();
+        let enum_dec = EnumDec {
+            variants: enum_dec
+                .variants
+                .into_iter()
+                .map(|variant| EnumVariant {
+                    params: variant
+                        .params
+                        .into_iter()
+                        .map(|param| substitute_type_vars(param, &type_args))
+                        .collect(),
+                    ..variant
+                })
+                .collect(),
+            ..enum_dec
+        };
+
+        for variant in &enum_dec.variants {
+            for param in &variant.params {
+                self.type_uses
+                    .push((param.clone(), scope_index, variant.span));
             }
         }
-        type_dec
+
+        let enum_type = if fresh_vars.is_empty() {
+            TypeExpr::TypeRef(enum_dec.identifier.clone())
+        } else {
+            TypeExpr::GenericRef(enum_dec.identifier.clone(), fresh_vars)
+        };
+        self.declare_type(
+            scope_index,
+            enum_dec.identifier.clone(),
+            TypeExpr::EnumDec(enum_dec.clone()),
+            enum_dec.span,
+        );
+
+        for variant in &enum_dec.variants {
+            let mut variant_identifier = enum_dec.identifier.clone();
+            variant_identifier.name.extend(variant.name.name.clone());
+            let value_type =
+                TypeExpr::Variant(Box::new(enum_type.clone()), variant_identifier.clone());
+            let variant_type = if variant.params.is_empty() {
+                value_type
+            } else {
+                TypeExpr::FunctionDefinition {
+                    type_identifier: variant_identifier.clone(),
+                    parameters: variant.params.clone(),
+                    return_type: Box::new(value_type),
+                }
+            };
+            let variant_type = if scheme_vars.is_empty() {
+                variant_type
+            } else {
+                TypeExpr::Scheme {
+                    type_vars: scheme_vars.clone(),
+                    type_expr: Box::new(variant_type),
+                }
+            };
+            self.declare_value(
+                scope_index,
+                variant_identifier.name.join("."),
+                variant_type,
+                variant.span,
+            );
+            self.declare_type(
+                scope_index,
+                variant_identifier.clone(),
+                TypeExpr::Variant(
+                    Box::new(TypeExpr::TypeRef(enum_dec.identifier.clone())),
+                    variant_identifier,
+                ),
+                variant.span,
+            );
+        }
+
+        enum_dec
+    }
+
+    /**
+     * `if x is Animal.Dog` gives the true branch a scope where x has the
+     * variant's type. Any other condition doesn't narrow anything, so the
+     * branch is bound in the same scope
+     */
+    fn narrowed_scope(&mut self, scope_index: usize, condition: &Expr) -> usize {
+        let ExprKind::Is {
+            subject,
+            variant,
+            negated: false,
+        } = &condition.kind
+        else {
+            return scope_index;
+        };
+        let ExprKind::ValueReference(MixedIdentifier::Identifier(identifier), _) = &subject.kind
+        else {
+            return scope_index;
+        };
+        let Some(subject_symbol) = self.find_value_symbol(scope_index, &identifier.name) else {
+            return scope_index;
+        };
+
+        // shadows the subject, so it's not a redeclaration
+        let narrowed_scope = self.new_child_scope(scope_index);
+        self.scopes[narrowed_scope].value_symbols.insert(
+            identifier.name.clone(),
+            ValueSymbol {
+                name: identifier.name.clone(),
+                type_expr: TypeExpr::Variant(Box::new(subject_symbol.type_expr), variant.clone()),
+                scope_index: narrowed_scope,
+            },
+        );
+        narrowed_scope
     }
 
     pub fn bind_statement(&mut self, scope_index: usize, expr: BlockStatement) -> BlockStatement {
@@ -225,10 +514,11 @@ impl ScopeTree {
     }
 
     pub fn bind_expression(&mut self, scope_index: usize, expr: Expr) -> Expr {
-        match expr {
-            Expr::BlockExpression(exprs, _) => {
+        let span = expr.span;
+        let kind = match expr.kind {
+            ExprKind::BlockExpression(exprs, _) => {
                 let block_scope = self.new_child_scope(scope_index);
-                Expr::BlockExpression(
+                ExprKind::BlockExpression(
                     exprs
                         .iter()
                         .map(|statement| -> BlockStatement {
@@ -238,47 +528,135 @@ impl ScopeTree {
                     Some(block_scope),
                 )
             }
-            Expr::Binary(left, op, right) => Expr::Binary(
+            ExprKind::Template(parts) => ExprKind::Template(
+                parts
+                    .into_iter()
+                    .map(|part| match part {
+                        TemplatePart::Expr(expr) => {
+                            TemplatePart::Expr(self.bind_expression(scope_index, expr))
+                        }
+                        text => text,
+                    })
+                    .collect(),
+            ),
+            ExprKind::Is {
+                subject,
+                variant,
+                negated,
+            } => ExprKind::Is {
+                subject: Box::new(self.bind_expression(scope_index, *subject)),
+                variant,
+                negated,
+            },
+            ExprKind::Unary(op, operand) => {
+                ExprKind::Unary(op, Box::new(self.bind_expression(scope_index, *operand)))
+            }
+            ExprKind::Binary(left, op, right) => ExprKind::Binary(
                 Box::new(self.bind_expression(scope_index, *left)),
                 op,
                 Box::new(self.bind_expression(scope_index, *right)),
             ),
-            Expr::Record(type_identifier, members) => Expr::Record(
-                type_identifier,
-                members
-                    .iter()
-                    .map(|m| ObjectMember {
-                        key: m.clone().key,
-                        value: self.bind_expression(scope_index, m.clone().value),
-                    })
-                    .collect(),
-            ),
-            Expr::Array(type_expr, exprs) => Expr::Array(
-                type_expr,
+            ExprKind::Record(type_identifier, members, _) => {
+                if let Some(type_identifier) = &type_identifier {
+                    self.type_uses.push((
+                        TypeExpr::TypeRef(type_identifier.clone()),
+                        scope_index,
+                        span,
+                    ));
+                }
+                ExprKind::Record(
+                    type_identifier,
+                    members
+                        .iter()
+                        .map(|m| ObjectMember {
+                            key: m.clone().key,
+                            value: self.bind_expression(scope_index, m.clone().value),
+                            span: m.span,
+                        })
+                        .collect(),
+                    Some(self.create_type_var()),
+                )
+            }
+            // every literal gets its own element type
+            ExprKind::Array(_, exprs) => ExprKind::Array(
+                self.create_type_var(),
                 exprs
                     .iter()
                     .map(|expr| self.bind_expression(scope_index, expr.clone()))
                     .collect(),
             ),
-            Expr::DotCall(callee, member_identifier) => Expr::DotCall(
+            ExprKind::Index(collection, index, _) => ExprKind::Index(
+                Box::new(self.bind_expression(scope_index, *collection)),
+                Box::new(self.bind_expression(scope_index, *index)),
+                Some(self.create_type_var()),
+            ),
+            ExprKind::DotCall(callee, member_identifier) => ExprKind::DotCall(
                 Box::new(self.bind_expression(scope_index, *callee)),
                 member_identifier,
             ),
-            Expr::FunctionCall {
+            ExprKind::FunctionCall {
                 callee,
                 args,
                 generic_args,
-            } => Expr::FunctionCall {
-                callee: Box::new(self.bind_expression(scope_index, *callee)),
-                args: args
-                    .iter()
-                    .map(|arg| self.bind_expression(scope_index, arg.clone()))
-                    .collect(),
-                generic_args,
-            },
-            Expr::Match(_subject, _clauses) => todo!(),
-            Expr::IfElse(_, _, _) => todo!(),
-            Expr::FunctionDefinition {
+            } => {
+                for generic_arg in &generic_args {
+                    self.type_uses
+                        .push((generic_arg.clone(), scope_index, span));
+                }
+                ExprKind::FunctionCall {
+                    callee: Box::new(self.bind_expression(scope_index, *callee)),
+                    args: args
+                        .iter()
+                        .map(|arg| self.bind_expression(scope_index, arg.clone()))
+                        .collect(),
+                    generic_args,
+                }
+            }
+            ExprKind::Match(subject, clauses, _) => {
+                let subject = self.bind_expression(scope_index, *subject);
+                let bound_clauses = clauses
+                    .into_iter()
+                    .map(|clause| {
+                        let clause_scope = self.new_child_scope(scope_index);
+                        let bindings = match &clause.pattern {
+                            Pattern::ValueRef(identifier) => vec![identifier.clone()],
+                            Pattern::Variant(_, bindings) => bindings.clone(),
+                            _ => Vec::new(),
+                        };
+                        for identifier in bindings {
+                            let binding_type = self.create_type_var();
+                            self.declare_value(
+                                clause_scope,
+                                identifier.name,
+                                binding_type,
+                                clause.span,
+                            );
+                        }
+                        MatchClause {
+                            body: self.bind_expression(clause_scope, clause.body),
+                            scope: Some(clause_scope),
+                            ..clause
+                        }
+                    })
+                    .collect();
+                let match_type = self.create_type_var();
+                ExprKind::Match(Box::new(subject), bound_clauses, Some(match_type))
+            }
+            ExprKind::IfElse(condition, true_branch, false_branch, _) => {
+                // branches are blocks (or an else if), so each gets its own scope
+                let condition = self.bind_expression(scope_index, *condition);
+                let true_scope = self.narrowed_scope(scope_index, &condition);
+                let true_branch = self.bind_expression(true_scope, *true_branch);
+                let false_branch = self.bind_expression(scope_index, *false_branch);
+                let if_type = self.create_type_var();
+                ExprKind::IfElse(
+                    Box::new(condition),
+                    Box::new(true_branch),
+                    Box::new(false_branch),
+                    Some(if_type),
+                )
+            }
+            ExprKind::FunctionDefinition {
                 parameters,
                 return_type,
                 body,
@@ -294,25 +672,31 @@ impl ScopeTree {
                 let bound_params: Vec<FunctionParameter> = parameters
                     .iter()
                     .map(|p| -> FunctionParameter {
-                        let param_type = p
-                            .type_expr
-                            .clone()
-                            .unwrap_or(self.create_type_var(scope_index));
-                        self.create_value_symbol(
+                        if let Some(type_expr) = &p.type_expr {
+                            self.type_uses
+                                .push((type_expr.clone(), scope_index, p.span));
+                        }
+                        let param_type = p.type_expr.clone().unwrap_or(self.create_type_var());
+                        self.declare_value(
                             fn_scope_index,
                             p.identifier.clone().name,
                             param_type.clone(),
+                            p.span,
                         );
                         FunctionParameter {
                             identifier: p.identifier.clone(),
                             type_expr: Some(param_type),
+                            span: p.span,
                         }
                     })
                     .collect();
 
-                let return_type = return_type.unwrap_or(self.create_type_var(scope_index));
+                if let Some(type_expr) = &return_type {
+                    self.type_uses.push((type_expr.clone(), scope_index, span));
+                }
+                let return_type = return_type.unwrap_or(self.create_type_var());
 
-                let fn_expr = Expr::FunctionDefinition {
+                let fn_kind = ExprKind::FunctionDefinition {
                     parameters: bound_params.clone(),
                     return_type: Some(return_type.clone()),
                     body: Box::new(self.bind_expression(fn_scope_index, *body)),
@@ -330,37 +714,50 @@ impl ScopeTree {
                         .collect(),
                     return_type: Box::new(return_type),
                 };
-                self.create_type_symbol(
+                self.declare_type(
                     scope_index,
                     TypeIdentifier {
                         name: vec![fn_identifier.name],
                     },
                     fn_type,
+                    span,
                 );
 
-                fn_expr
+                fn_kind
             }
 
+            ExprKind::ValueReference(mixed_identifier, _) => {
+                ExprKind::ValueReference(mixed_identifier, Some(self.create_type_var()))
+            }
             // No scope operation required
-            Expr::Number(_) => expr,
-            Expr::String(_) => expr,
-            Expr::Boolean(_) => expr,
-            Expr::ValueReference(_) => expr,
-            Expr::Void => expr,
-        }
+            kind @ (ExprKind::Number(_)
+            | ExprKind::String(_)
+            | ExprKind::Boolean(_)
+            | ExprKind::Void
+            | ExprKind::Error) => kind,
+        };
+
+        Expr::new(kind, span)
     }
 
-    pub fn create_type_var(&mut self, scope_index: usize) -> TypeExpr {
+    /**
+     * Type var names are unique across the whole tree, so they all live in the
+     * root scope. A var created while binding a block can end up in constraints
+     * of the parent scope, e.g. the value of an if branch
+     */
+    pub fn create_type_var(&mut self) -> TypeExpr {
         let name = format!("t{}", self.next_type_var);
         let inference_required = TypeExpr::InferenceRequired(Some(TypeIdentifier {
             name: vec![name.clone()],
         }));
         self.next_type_var += 1;
         self.create_type_symbol(
-            scope_index,
+            0,
             TypeIdentifier { name: vec![name] },
             inference_required.clone(),
-        );
+            Span::default(),
+        )
+        .expect("type var names are unique");
 
         inference_required
     }
@@ -370,14 +767,15 @@ impl ScopeTree {
         scope_index: usize,
         identifier: TypeIdentifier,
         type_expr: TypeExpr,
-    ) -> &TypeSymbol {
+        span: Span,
+    ) -> Result<&TypeSymbol, CompilerError> {
         let joined_name = identifier.name.join(".");
         let existing = self.find_type_symbol(scope_index, identifier);
         if existing.is_some() {
-            panic!(
-                "Cannot redeclare type symbol with name {}",
-                joined_name.clone()
-            );
+            return Err(CompilerError::Redeclaration {
+                name: joined_name,
+                span,
+            });
         }
         let scope = self
             .scopes
@@ -391,45 +789,10 @@ impl ScopeTree {
                 type_expr,
             },
         );
-        self.scopes[scope_index]
+        Ok(self.scopes[scope_index]
             .type_symbols
             .get(&joined_name.clone())
-            .expect("type symbol")
-    }
-
-    pub fn update_type_symbol(
-        &mut self,
-        scope_index: usize,
-        identifier: TypeIdentifier,
-        type_expr: TypeExpr,
-    ) {
-        let joined_name = identifier.name.join(".");
-        let mut current_scope = self
-            .scopes
-            .get_mut(scope_index)
-            .expect("Scope should exist");
-
-        while !current_scope
-            .type_symbols
-            .contains_key(joined_name.as_str())
-        {
-            if let Some(parent_index) = current_scope.parent {
-                current_scope = self
-                    .scopes
-                    .get_mut(parent_index)
-                    .expect("parent scope should exist");
-            } else {
-                println!("identifier: {:?} - scope: {}", joined_name, scope_index);
-                panic!("got to root scope without finding symbol to update");
-            }
-        }
-
-        let type_symbol = current_scope
-            .type_symbols
-            .get_mut(joined_name.as_str())
-            .unwrap_or_else(|| panic!("Type {} should be in scope {}", joined_name, scope_index));
-
-        type_symbol.type_expr = type_expr;
+            .expect("type symbol"))
     }
 
     pub fn find_type_symbol(
@@ -465,9 +828,13 @@ impl ScopeTree {
         scope_index: usize,
         identifier: String,
         type_expr: TypeExpr,
-    ) -> &ValueSymbol {
+        span: Span,
+    ) -> Result<&ValueSymbol, CompilerError> {
         if self.find_value_symbol(scope_index, &identifier).is_some() {
-            panic!("Cannot redeclare value symbol with name {}", identifier);
+            return Err(CompilerError::Redeclaration {
+                name: identifier,
+                span,
+            });
         }
 
         let scope = self
@@ -484,10 +851,10 @@ impl ScopeTree {
             },
         );
 
-        scope
+        Ok(scope
             .value_symbols
             .get(&identifier)
-            .expect("Recently added value symbol should be retrievable")
+            .expect("Recently added value symbol should be retrievable"))
     }
 
     pub fn find_value_symbol(&self, scope_index: usize, identifier: &str) -> Option<ValueSymbol> {
@@ -537,7 +904,7 @@ impl ScopeTree {
                         resolved_type.clone()
                     }
                 } else {
-                    println!("no type symbol when trying to resolve type");
+                    eprintln!("no type symbol when trying to resolve type");
                     // Ported this from the old TS compiler but...
                     if let Some(scope) = self.scopes.get(scope_index) {
                         let parent = scope.parent.unwrap_or(0);
@@ -554,6 +921,11 @@ impl ScopeTree {
         }
     }
 
+    /** swaps a scheme's vars for fresh ones, other types are returned as is */
+    pub fn instantiate(&mut self, type_expr: TypeExpr) -> TypeExpr {
+        instantiate(type_expr, || self.create_type_var())
+    }
+
     pub fn scope_depth(&self, scope_index: usize) -> usize {
         let mut depth = 0;
         let mut current_scope = &self.scopes[scope_index];
@@ -573,9 +945,7 @@ impl ScopeTree {
         member_name: Identifier,
     ) -> Option<TypeExpr> {
         let module_map = self.module_map.read().expect("can read module_map");
-        let modules = module_map
-            .find_modules_by_name(module_name.as_str())
-            .expect("module by name");
+        let modules = module_map.find_modules_by_name(module_name.as_str())?;
         // Find the particular module that has the member_name
         // TODO: Scope is done by "Program" but should be by "Module"
         let resolved_module_index = modules.iter().find(|&&module_index| {
@@ -590,12 +960,10 @@ impl ScopeTree {
                 let resolved_module = module_map.get_module(*index);
                 match &resolved_module.program {
                     Some(program) => {
-                        let type_symbol = self
-                            .find_value_symbol(
-                                program.scope.expect("program scope"),
-                                &member_name.name,
-                            )
-                            .expect("type symbol");
+                        let type_symbol = self.find_value_symbol(
+                            program.scope.expect("program scope"),
+                            &member_name.name,
+                        )?;
                         Some(type_symbol.type_expr)
                     }
                     None => None,
@@ -606,13 +974,89 @@ impl ScopeTree {
     }
 }
 
+/**
+ * swaps a scheme's vars for ones made by fresh_var, other types are returned
+ * as is. Binding and solving each mint their own vars
+ */
+pub fn instantiate(type_expr: TypeExpr, mut fresh_var: impl FnMut() -> TypeExpr) -> TypeExpr {
+    match type_expr {
+        TypeExpr::Scheme {
+            type_vars,
+            type_expr,
+        } => {
+            let fresh_vars: HashMap<String, TypeExpr> = type_vars
+                .iter()
+                .map(|type_var| (type_var.name.join("."), fresh_var()))
+                .collect();
+            substitute_type_vars(*type_expr, &fresh_vars)
+        }
+        _ => type_expr,
+    }
+}
+
+/**
+ * replaces references to type vars, e.g. the T in Option<T> or the vars of a
+ * scheme, with their args
+ */
+pub fn substitute_type_vars(
+    type_expr: TypeExpr,
+    type_args: &HashMap<String, TypeExpr>,
+) -> TypeExpr {
+    match type_expr {
+        TypeExpr::TypeRef(ref type_identifier)
+        | TypeExpr::InferenceRequired(Some(ref type_identifier)) => type_args
+            .get(&type_identifier.name.join("."))
+            .cloned()
+            .unwrap_or(type_expr),
+        TypeExpr::Record(members) => TypeExpr::Record(
+            members
+                .into_iter()
+                .map(|member| RecordTypeMemeber {
+                    type_expr: substitute_type_vars(member.type_expr, type_args),
+                    ..member
+                })
+                .collect(),
+        ),
+        TypeExpr::Array(element_type) => {
+            TypeExpr::Array(Box::new(substitute_type_vars(*element_type, type_args)))
+        }
+        TypeExpr::Variant(enum_type, variant) => TypeExpr::Variant(
+            Box::new(substitute_type_vars(*enum_type, type_args)),
+            variant,
+        ),
+        TypeExpr::GenericRef(type_identifier, args) => TypeExpr::GenericRef(
+            type_identifier,
+            args.into_iter()
+                .map(|arg| substitute_type_vars(arg, type_args))
+                .collect(),
+        ),
+        TypeExpr::FunctionDefinition {
+            type_identifier,
+            parameters,
+            return_type,
+        } => TypeExpr::FunctionDefinition {
+            type_identifier,
+            parameters: parameters
+                .into_iter()
+                .map(|param| substitute_type_vars(param, type_args))
+                .collect(),
+            return_type: Box::new(substitute_type_vars(*return_type, type_args)),
+        },
+        _ => type_expr,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn new_scope_tree() -> ScopeTree {
+        ScopeTree::new(Arc::new(RwLock::new(ModuleMap::new())))
+    }
+
     #[test]
     fn new_scope_tree_contains_initial_scope() {
-        let tree = ScopeTree::new();
+        let tree = new_scope_tree();
         assert_eq!(
             tree.scopes.len(),
             1,
@@ -622,7 +1066,7 @@ mod tests {
 
     #[test]
     fn new_child_scope_creates_and_links_scope_correctly() {
-        let mut tree = ScopeTree::new();
+        let mut tree = new_scope_tree();
         let parent_index = 0;
         let child_index = tree.new_child_scope(parent_index);
 
@@ -644,12 +1088,13 @@ mod tests {
 
     #[test]
     fn find_type_symbol_in_current_scope() {
-        let mut tree = ScopeTree::new();
+        let mut tree = new_scope_tree();
         let identifier = TypeIdentifier {
             name: vec!["True".to_string()],
         };
-        let type_expr = TypeExpr::TypeRef(identifier);
-        tree.create_type_symbol(0, identifier.clone(), type_expr.clone());
+        let type_expr = TypeExpr::TypeRef(identifier.clone());
+        tree.create_type_symbol(0, identifier.clone(), type_expr.clone(), Span::default())
+            .unwrap();
 
         let symbol = tree
             .find_type_symbol(0, identifier.clone())
@@ -667,22 +1112,30 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Cannot redeclare type symbol with name")]
-    fn create_type_symbol_panics_on_redeclaration() {
-        let mut tree = ScopeTree::new();
+    fn create_type_symbol_errors_on_redeclaration() {
+        let mut tree = new_scope_tree();
         let identifier = TypeIdentifier {
             name: vec!["SomeType".to_string()],
         };
         let type_expr = TypeExpr::Number;
-        tree.create_type_symbol(0, identifier.clone(), type_expr.clone());
+        tree.create_type_symbol(0, identifier.clone(), type_expr.clone(), Span::default())
+            .unwrap();
 
-        // This should panic due to redeclaration
-        tree.create_type_symbol(0, identifier.clone(), type_expr.clone());
+        // This should error due to redeclaration
+        let result =
+            tree.create_type_symbol(0, identifier.clone(), type_expr.clone(), Span::default());
+        assert_eq!(
+            result.unwrap_err(),
+            CompilerError::Redeclaration {
+                name: "SomeType".to_string(),
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
     fn find_type_symbol_searches_parent_scopes() {
-        let mut tree = ScopeTree::new();
+        let mut tree = new_scope_tree();
         let parent_scope_index = 0;
         let child_scope_index = tree.new_child_scope(parent_scope_index);
 
@@ -690,7 +1143,13 @@ mod tests {
         let identifier = TypeIdentifier {
             name: vec!["SomeType".to_string()],
         };
-        tree.create_type_symbol(parent_scope_index, identifier.clone(), type_expr.clone());
+        tree.create_type_symbol(
+            parent_scope_index,
+            identifier.clone(),
+            type_expr.clone(),
+            Span::default(),
+        )
+        .unwrap();
 
         let symbol = tree
             .find_type_symbol(child_scope_index, identifier.clone())
@@ -709,12 +1168,18 @@ mod tests {
 
     #[test]
     fn create_value_symbol_adds_symbol_correctly() {
-        let mut tree = ScopeTree::new();
+        let mut tree = new_scope_tree();
         let scope_index = 0;
         let identifier = "value1".to_string();
         let type_expr = TypeExpr::String;
 
-        tree.create_value_symbol(scope_index, identifier.clone(), type_expr.clone());
+        tree.create_value_symbol(
+            scope_index,
+            identifier.clone(),
+            type_expr.clone(),
+            Span::default(),
+        )
+        .unwrap();
 
         assert!(
             tree.scopes[scope_index]
@@ -725,28 +1190,51 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Cannot redeclare value symbol with name")]
-    fn create_value_symbol_panics_on_redeclaration() {
-        let mut tree = ScopeTree::new();
+    fn create_value_symbol_errors_on_redeclaration() {
+        let mut tree = new_scope_tree();
         let scope_index = 0;
         let identifier = "value1".to_string();
         let type_expr = TypeExpr::String;
 
         // First declaration should succeed
-        tree.create_value_symbol(scope_index, identifier.clone(), type_expr.clone());
+        tree.create_value_symbol(
+            scope_index,
+            identifier.clone(),
+            type_expr.clone(),
+            Span::default(),
+        )
+        .unwrap();
 
-        // Attempting to redeclare should panic
-        tree.create_value_symbol(scope_index, identifier.clone(), type_expr.clone());
+        // Attempting to redeclare should error
+        let result = tree.create_value_symbol(
+            scope_index,
+            identifier.clone(),
+            type_expr.clone(),
+            Span::default(),
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            CompilerError::Redeclaration {
+                name: identifier,
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
     fn find_value_symbol_in_current_scope() {
-        let mut tree = ScopeTree::new();
+        let mut tree = new_scope_tree();
         let scope_index = 0;
         let identifier = "value1".to_string();
         let type_expr = TypeExpr::String;
 
-        tree.create_value_symbol(scope_index, identifier.clone(), type_expr.clone());
+        tree.create_value_symbol(
+            scope_index,
+            identifier.clone(),
+            type_expr.clone(),
+            Span::default(),
+        )
+        .unwrap();
 
         let symbol = tree
             .find_value_symbol(scope_index, &identifier)
@@ -764,14 +1252,20 @@ mod tests {
 
     #[test]
     fn find_value_symbol_searches_parent_scopes() {
-        let mut tree = ScopeTree::new();
+        let mut tree = new_scope_tree();
         let parent_scope_index = 0;
         let child_scope_index = tree.new_child_scope(parent_scope_index);
         let identifier = "value1".to_string();
         let type_expr = TypeExpr::String;
 
         // Define symbol in parent scope
-        tree.create_value_symbol(parent_scope_index, identifier.clone(), type_expr.clone());
+        tree.create_value_symbol(
+            parent_scope_index,
+            identifier.clone(),
+            type_expr.clone(),
+            Span::default(),
+        )
+        .unwrap();
 
         // Search for it in child scope
         let symbol = tree
@@ -796,9 +1290,12 @@ mod tests {
 
     fn create_const_dec(name: &str, value: Expr, type_annotation: Option<TypeExpr>) -> ConstDec {
         ConstDec {
+            doc: None,
             identifier: create_identifier(name),
             type_annotation,
+            type_annotation_span: None,
             value: Box::new(value),
+            span: Span::default(),
         }
     }
 
@@ -808,10 +1305,15 @@ mod tests {
             module_dec: ModuleDec {
                 name: vec!["TestModule".to_string()],
                 exports: vec![],
+                span: Span::default(),
             },
             imports: vec![],
             statements: vec![
-                TopStatement::ConstDec(create_const_dec("x", Expr::Number("42".to_string()), None)),
+                TopStatement::ConstDec(create_const_dec(
+                    "x",
+                    Expr::new(ExprKind::Number("42".to_string()), Span::default()),
+                    None,
+                )),
                 // Add more statements as needed for comprehensive tests
             ],
             scope: None,
@@ -820,12 +1322,12 @@ mod tests {
 
     #[test]
     fn bind_const_dec_with_type_annotation() {
-        let mut scope_tree = ScopeTree::new();
+        let mut scope_tree = new_scope_tree();
         let scope_index = scope_tree.new_program_scope();
 
         let const_dec = create_const_dec(
             "x",
-            Expr::String("test".to_string()),
+            Expr::new(ExprKind::String("test".to_string()), Span::default()),
             Some(TypeExpr::String),
         );
 
@@ -840,10 +1342,14 @@ mod tests {
 
     #[test]
     fn bind_const_dec_without_type_annotation() {
-        let mut scope_tree = ScopeTree::new();
+        let mut scope_tree = new_scope_tree();
         let scope_index = scope_tree.new_program_scope();
 
-        let const_dec = create_const_dec("y", Expr::Boolean(true), None);
+        let const_dec = create_const_dec(
+            "y",
+            Expr::new(ExprKind::Boolean(true), Span::default()),
+            None,
+        );
         scope_tree.bind_const_dec(scope_index, const_dec);
 
         // Verify that 'y' is assigned a type var (assuming create_type_var generates a unique TypeExpr)
@@ -858,7 +1364,7 @@ mod tests {
 
     #[test]
     fn test_bind_program_basic() {
-        let mut scope_tree = ScopeTree::new(); // Assuming you have such a constructor
+        let mut scope_tree = new_scope_tree();
         let program = setup_test_program();
         let bound_program = scope_tree.bind_program(program).expect("bound program");
 
@@ -874,4 +1380,214 @@ mod tests {
             self::panic!("First statement should be a ConstDec");
         }
     }
+
+    #[test]
+    fn bind_match_gives_each_clause_a_scope() {
+        let mut scope_tree = new_scope_tree();
+        let scope_index = scope_tree.new_program_scope();
+        let clause = |pattern: Pattern| MatchClause {
+            pattern,
+            pattern_span: Span::default(),
+            body: Expr::new(ExprKind::String("body".to_string()), Span::default()),
+            scope: None,
+            span: Span::default(),
+        };
+        let match_expr = Expr::new(
+            ExprKind::Match(
+                Box::new(Expr::new(
+                    ExprKind::Number("1".to_string()),
+                    Span::default(),
+                )),
+                vec![
+                    clause(Pattern::Number("1".to_string())),
+                    clause(Pattern::ValueRef(create_identifier("other"))),
+                ],
+                None,
+            ),
+            Span::default(),
+        );
+
+        let bound = scope_tree.bind_expression(scope_index, match_expr);
+        let ExprKind::Match(_, clauses, match_type) = bound.kind else {
+            self::panic!("expected a match expression");
+        };
+
+        assert!(matches!(match_type, Some(TypeExpr::InferenceRequired(_))));
+        let clause_scopes: Vec<usize> = clauses.iter().map(|c| c.scope.unwrap()).collect();
+        assert_ne!(clause_scopes[0], clause_scopes[1]);
+        assert!(scope_tree
+            .find_value_symbol(clause_scopes[1], "other")
+            .is_some());
+        assert!(scope_tree
+            .find_value_symbol(clause_scopes[0], "other")
+            .is_none());
+    }
+
+    #[test]
+    fn bind_if_else_gives_each_branch_a_scope() {
+        let mut scope_tree = new_scope_tree();
+        let scope_index = scope_tree.new_program_scope();
+        let branch = |name: &str| {
+            Expr::new(
+                ExprKind::BlockExpression(
+                    vec![BlockStatement::ConstDec(create_const_dec(
+                        name,
+                        Expr::new(ExprKind::Number("1".to_string()), Span::default()),
+                        None,
+                    ))],
+                    None,
+                ),
+                Span::default(),
+            )
+        };
+        let if_expr = Expr::new(
+            ExprKind::IfElse(
+                Box::new(Expr::new(ExprKind::Boolean(true), Span::default())),
+                Box::new(branch("yes")),
+                Box::new(branch("no")),
+                None,
+            ),
+            Span::default(),
+        );
+
+        let bound = scope_tree.bind_expression(scope_index, if_expr);
+        let ExprKind::IfElse(_, true_branch, false_branch, if_type) = bound.kind else {
+            self::panic!("expected an if expression");
+        };
+        let ExprKind::BlockExpression(_, Some(true_scope)) = true_branch.kind else {
+            self::panic!("expected the true branch to be a bound block");
+        };
+        let ExprKind::BlockExpression(_, Some(false_scope)) = false_branch.kind else {
+            self::panic!("expected the false branch to be a bound block");
+        };
+
+        assert!(matches!(if_type, Some(TypeExpr::InferenceRequired(_))));
+        assert_ne!(true_scope, false_scope);
+        assert!(scope_tree.find_value_symbol(true_scope, "yes").is_some());
+        assert!(scope_tree.find_value_symbol(false_scope, "yes").is_none());
+    }
+
+    #[test]
+    fn bind_enum_registers_variants() {
+        let mut scope_tree = new_scope_tree();
+        let scope_index = scope_tree.new_program_scope();
+        let animal = TypeIdentifier {
+            name: vec!["Animal".to_string()],
+        };
+        let variant = |name: &str, params: Vec<TypeExpr>| EnumVariant {
+            name: TypeIdentifier {
+                name: vec![name.to_string()],
+            },
+            params,
+            span: Span::default(),
+        };
+        scope_tree.bind_enum_dec(
+            scope_index,
+            EnumDec {
+                doc: None,
+                identifier: animal.clone(),
+                type_vars: vec![],
+                variants: vec![
+                    variant("Fish", vec![TypeExpr::Number]),
+                    variant("Cat", vec![]),
+                ],
+                span: Span::default(),
+            },
+        );
+
+        assert!(matches!(
+            scope_tree.find_type_symbol(scope_index, animal.clone()),
+            Some(TypeSymbol {
+                type_expr: TypeExpr::EnumDec(_),
+                ..
+            })
+        ));
+        let fish = scope_tree
+            .find_value_symbol(scope_index, "Animal.Fish")
+            .unwrap();
+        assert!(matches!(
+            fish.type_expr,
+            TypeExpr::FunctionDefinition { ref parameters, .. } if *parameters == vec![TypeExpr::Number]
+        ));
+        let cat = scope_tree
+            .find_value_symbol(scope_index, "Animal.Cat")
+            .unwrap();
+        let cat_identifier = TypeIdentifier {
+            name: vec!["Animal".to_string(), "Cat".to_string()],
+        };
+        assert_eq!(
+            cat.type_expr,
+            TypeExpr::Variant(Box::new(TypeExpr::TypeRef(animal)), cat_identifier)
+        );
+    }
+
+    #[test]
+    fn bind_program_reports_every_mistake() {
+        let source = "module Main
+            from Missing.Module
+            const a = 1
+            const a = 2";
+        let tokens = crate::lexer::Lexer::new(source.to_string()).tokenize();
+        let program = crate::parser::Parser::new(tokens).parse().unwrap();
+        let mut scope_tree = new_scope_tree();
+
+        let error = scope_tree.bind_program(program).unwrap_err();
+        assert!(
+            matches!(
+                error,
+                CompilerError::Multiple(ref errors) if matches!(
+                    errors.as_slice(),
+                    [
+                        CompilerError::UnknownModule { name, .. },
+                        CompilerError::Redeclaration { .. },
+                    ] if name == "Missing.Module"
+                )
+            ),
+            "{:#?}",
+            error
+        );
+        assert!(scope_tree.errors.is_empty());
+    }
+
+    #[test]
+    fn bind_program_reports_unknown_type_names() {
+        let bind = |source: &str| {
+            let tokens = crate::lexer::Lexer::new(source.to_string()).tokenize();
+            let program = crate::parser::Parser::new(tokens).parse().unwrap();
+            new_scope_tree().bind_program(program)
+        };
+
+        let error = bind(
+            "module Main
+            const x: Foo = 1",
+        )
+        .unwrap_err();
+        assert!(
+            matches!(error, CompilerError::UndefinedName { ref name, .. } if name == "Foo"),
+            "{:#?}",
+            error
+        );
+        assert_eq!(error.diagnostics()[0].code, Some("E0201"));
+
+        let error = bind(
+            "module Main
+            const y = Foo { a: 1 }",
+        )
+        .unwrap_err();
+        assert!(
+            matches!(error, CompilerError::UndefinedName { ref name, .. } if name == "Foo"),
+            "{:#?}",
+            error
+        );
+
+        // types can be used before they're declared, and type vars are only names
+        assert!(bind(
+            "module Main
+            const b: Box<Animal.Dog> = { value: Animal.Dog }
+            const f = (box: Box<Number>): Animal => Animal.Cat
+            type Box<T> = { value: T }
+            enum Animal { Dog, Cat }"
+        )
+        .is_ok());
+    }
 }
@@ -0,0 +1,106 @@
+use std::{fs, path::PathBuf, process::Command};
+
+/** a fresh directory to run the compiler in, it writes ./build wherever it runs */
+fn work_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fyg-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("can create work dir");
+    dir
+}
+
+#[test]
+fn json_message_format_only_writes_diagnostics_to_stdout() {
+    let dir = work_dir("json");
+    fs::write(
+        dir.join("main.fyg"),
+        "module Main
+const a: String = 1
+const b: Number = `two`
+",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_fyg"))
+        .args(["build", "--message-format=json", "main.fyg"])
+        .current_dir(&dir)
+        .output()
+        .expect("fyg should run");
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let diagnostics: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|_| panic!("not JSON: {}", line)))
+        .collect();
+    assert_eq!(diagnostics.len(), 2, "{}", stdout);
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic["code"] == "E0101"));
+}
+
+#[test]
+fn go_mod_asks_for_a_go_with_generics() {
+    let dir = work_dir("go-mod");
+    fs::write(
+        dir.join("main.fyg"),
+        "module Main
+type Box<T> = { value: T }
+const boxed: Box<Number> = { value: 1 }
+",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_fyg"))
+        .args(["build", "main.fyg"])
+        .current_dir(&dir)
+        .output()
+        .expect("fyg should run");
+    let go_mod = fs::read_to_string(dir.join("build/go.mod")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(go_mod, "module fygbuild\n\ngo 1.18\n");
+}
+
+#[test]
+fn calling_unknown_names_is_reported_not_a_crash() {
+    let dir = work_dir("unknown-call");
+    fs::create_dir_all(dir.join("stdlib")).unwrap();
+    fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/stdlib/Log.fyg"),
+        dir.join("stdlib/Log.fyg"),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("main.fyg"),
+        "module Main
+from Fyg.Log
+const d = zz(1)
+Log.nope(1)
+",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_fyg"))
+        .args(["build", "--message-format=json", "main.fyg"])
+        .current_dir(&dir)
+        .output()
+        .expect("fyg should run");
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let codes: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let diagnostic: serde_json::Value = serde_json::from_str(line).unwrap();
+            diagnostic["code"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(codes, ["E0201", "E0204"]);
+}